@@ -110,6 +110,10 @@ pub fn main() !void {
     std.debug.print("Result: {s}\n", .{return_value.calculate_return_value_string()});
 }
 
+export fn health_check_abi_version() u32 {
+    return 1;
+}
+
 export fn free_string(s: [*:0]const u8) void {
     const c_allocator = std.heap.c_allocator;
     const lenght = std.mem.len(s);
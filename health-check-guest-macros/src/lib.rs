@@ -0,0 +1,91 @@
+//! Attribute macros re-exported by `health-check-guest`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, ItemFn};
+
+/// Turns `fn(&Context) -> CheckResult` (or a function without arguments)
+/// into the exports of a worker module.
+#[proc_macro_attribute]
+pub fn health_check(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand(attr, item, false)
+}
+
+/// Like [`health_check`], but for runners, whose [`Context`] carries the
+/// payload of the triggering request.
+#[proc_macro_attribute]
+pub fn runner(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand(attr, item, true)
+}
+
+fn expand(attr: TokenStream, item: TokenStream, runner: bool) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "the attribute does not take arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let function = parse_macro_input!(item as ItemFn);
+    match exports(&function, runner) {
+        Ok(exports) => quote! {
+            #function
+            #exports
+        }
+        .into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn exports(function: &ItemFn, runner: bool) -> syn::Result<TokenStream2> {
+    let name = &function.sig.ident;
+    let call = match function.sig.inputs.len() {
+        0 => quote! { |_context: &::health_check_guest::Context| #name() },
+        1 => quote! { |context: &::health_check_guest::Context| #name(context) },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &function.sig.inputs,
+                "a check takes at most one argument, `&Context`",
+            ))
+        }
+    };
+    if function.sig.asyncness.is_some() {
+        return Err(syn::Error::new_spanned(
+            function.sig.asyncness,
+            "a check cannot be async",
+        ));
+    }
+
+    Ok(quote! {
+        #[cfg(target_family = "wasm")]
+        const _: () = {
+            #[no_mangle]
+            pub extern "C" fn _start() {
+                ::health_check_guest::__private::run_wasm(#call, #runner)
+            }
+        };
+
+        #[cfg(not(target_family = "wasm"))]
+        const _: () = {
+            #[no_mangle]
+            pub extern "C" fn health_check_abi_version() -> u32 {
+                ::health_check_guest::__private::ABI_VERSION
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn start(
+                context: *const ::health_check_guest::__private::HcContext,
+            ) -> *const ::std::os::raw::c_char {
+                ::health_check_guest::__private::run_native(context, #call, #runner)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn free_string(result: *const ::std::os::raw::c_char) {
+                ::health_check_guest::__private::free_string(result)
+            }
+        };
+    })
+}
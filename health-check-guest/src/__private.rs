@@ -0,0 +1,85 @@
+//! Entry points called by the code generated by the attribute macros.
+
+use crate::{CheckResult, Context, Payload};
+
+/// Runs the check as the `_start` of a Wasm module. Runners receive their
+/// payload as JSON on stdin, which is only read when the host announces one
+/// with `HEALTH_CHECK_PAYLOAD=1`.
+#[cfg(target_family = "wasm")]
+pub fn run_wasm(check: impl FnOnce(&Context) -> CheckResult, runner: bool) {
+    use std::io::Read;
+
+    let mut context = Context {
+        module_name: std::env::args().next().unwrap_or_default(),
+        config: std::env::var("HEALTH_CHECK_CONFIG")
+            .ok()
+            .and_then(|config| serde_json::from_str(&config).ok())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    if runner && std::env::var("HEALTH_CHECK_PAYLOAD").as_deref() == Ok("1") {
+        let mut payload = String::new();
+        if std::io::stdin().read_to_string(&mut payload).is_ok() && !payload.trim().is_empty() {
+            context.payload = serde_json::from_str::<Payload>(&payload).ok();
+        }
+    }
+
+    println!("{}", check(&context).to_json());
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub use crate::native::HcContext;
+
+#[cfg(not(target_family = "wasm"))]
+pub const ABI_VERSION: u32 = 2;
+
+/// Runs the check as the `start` export of a native module. A panic is
+/// reported as a crash instead of unwinding into the host.
+///
+/// # Safety
+///
+/// `context` must be the pointer the host passed to `start`.
+#[cfg(not(target_family = "wasm"))]
+pub unsafe fn run_native(
+    context: *const HcContext,
+    check: impl FnOnce(&Context) -> CheckResult,
+    runner: bool,
+) -> *const std::os::raw::c_char {
+    use crate::native::{read_string, ContextGuard};
+
+    let Some(host_context) = context.as_ref() else {
+        return std::ptr::null();
+    };
+    let _guard = ContextGuard::enter(context);
+
+    let payload = read_string(host_context.payload);
+    let context = Context {
+        module_name: read_string(host_context.module_name),
+        config: serde_json::from_str(&read_string(host_context.config)).unwrap_or_default(),
+        payload: if runner && !payload.is_empty() {
+            serde_json::from_str::<Payload>(&payload).ok()
+        } else {
+            None
+        },
+        deadline_unix_ms: Some(host_context.deadline_unix_ms),
+    };
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| check(&context)))
+        .unwrap_or_else(|_| CheckResult::crash().message("Check panicked"));
+
+    match std::ffi::CString::new(result.to_json()) {
+        Ok(val) => val.into_raw(),
+        Err(_) => std::ptr::null(),
+    }
+}
+
+/// # Safety
+///
+/// `result` must have been returned by [`run_native`].
+#[cfg(not(target_family = "wasm"))]
+pub unsafe fn free_string(result: *const std::os::raw::c_char) {
+    if !result.is_null() {
+        drop(std::ffi::CString::from_raw(result as *mut _));
+    }
+}
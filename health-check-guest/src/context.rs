@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// Arguments and environment of the thunder request that triggered a run.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Payload {
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// What the host tells a check about the current execution.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub module_name: String,
+    /// The module's `settings` from the host configuration.
+    pub config: serde_json::Value,
+    /// Only set for runners that were triggered with a payload.
+    pub payload: Option<Payload>,
+    /// Unix time in milliseconds by which the check should be done, if the
+    /// host passes one.
+    pub deadline_unix_ms: Option<u64>,
+}
+
+impl Context {
+    /// Reads one entry of the module's settings.
+    pub fn setting<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.config.get(key)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
@@ -0,0 +1,123 @@
+//! SDK for writing health-check modules in Rust.
+//!
+//! Annotate the check with [`health_check`] for a periodically polled worker
+//! or [`runner`] for a module triggered through thunder requests. The same
+//! crate, built as a `cdylib`, produces a native module with the context ABI
+//! or, with `--target wasm32-wasip1`, a Wasm module:
+//!
+//! ```ignore
+//! use health_check_guest::{health_check, CheckResult, Context};
+//!
+//! #[health_check]
+//! fn check(context: &Context) -> CheckResult {
+//!     let runs: u32 = health_check_guest::kv_get("runs")
+//!         .and_then(|runs| runs.parse().ok())
+//!         .unwrap_or(0);
+//!     CheckResult::ok()
+//!         .message(format!("checked {}", context.module_name))
+//!         .kv("runs", (runs + 1).to_string())
+//! }
+//! ```
+//!
+//! Name the resulting file after the host's conventions (`*.so`/`*.wasm` for
+//! workers, `*_run.so`/`*_run.wasm` for runners). The host helpers below only
+//! work on the thread running the check.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+mod context;
+#[cfg(not(target_family = "wasm"))]
+mod native;
+mod output;
+#[cfg(target_family = "wasm")]
+mod wasm;
+
+#[doc(hidden)]
+pub mod __private;
+
+pub use context::{Context, Payload};
+pub use health_check_guest_macros::{health_check, runner};
+pub use output::{CheckResult, Status};
+
+#[cfg(not(target_family = "wasm"))]
+use native as sys;
+#[cfg(target_family = "wasm")]
+use wasm as sys;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Debug = 0,
+    Info = 1,
+    Warn = 2,
+    Error = 3,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub timeout_ms: Option<u64>,
+}
+
+impl Request {
+    pub fn get(url: &str) -> Self {
+        Request {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Any status the server answered with, including error statuses. `error`
+/// is set instead when no response was received, e.g. because the host is
+/// not in the module's `allowed_hosts`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Response {
+    #[serde(default)]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+    pub error: Option<String>,
+}
+
+/// Reads a value from the host's key-value store, including values persisted
+/// by other modules.
+pub fn kv_get(key: &str) -> Option<String> {
+    sys::kv_get(key)
+}
+
+/// Stores a value in the host's key-value store. Returns false if the host
+/// could not persist it.
+pub fn kv_set(key: &str, value: &str) -> bool {
+    sys::kv_set(key, value)
+}
+
+/// Writes a line to the module's log on the host.
+pub fn log(level: Level, message: &str) {
+    sys::log(level as i32, message)
+}
+
+/// Reports a metric, merged into the metrics of the module's result.
+pub fn emit_metric(name: &str, value: f64) {
+    sys::emit_metric(name, value)
+}
+
+/// Milliseconds since the Unix epoch.
+pub fn now() -> u64 {
+    sys::now()
+}
+
+/// Sends an HTTP request through the host, limited to the module's
+/// `allowed_hosts`. Returns None if the host could not read the request.
+/// Native modules do their own networking.
+#[cfg(target_family = "wasm")]
+pub fn http_request(request: &Request) -> Option<Response> {
+    wasm::http_request(request)
+}
@@ -0,0 +1,102 @@
+//! Host callbacks of the context ABI, see `include/health_check.h` in the
+//! host repository.
+
+use std::{
+    cell::Cell,
+    ffi::{c_void, CStr, CString},
+    os::raw::c_char,
+};
+
+#[repr(C)]
+pub struct HcContext {
+    pub abi_version: u32,
+    pub module_name: *const c_char,
+    pub config: *const c_char,
+    pub payload: *const c_char,
+    pub env: *const c_char,
+    pub deadline_unix_ms: u64,
+    pub host_data: *mut c_void,
+    pub log: unsafe extern "C" fn(*mut c_void, i32, *const c_char),
+    pub kv_get: unsafe extern "C" fn(*mut c_void, *const c_char) -> *const c_char,
+    pub kv_set: unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char) -> i32,
+    pub emit_metric: unsafe extern "C" fn(*mut c_void, *const c_char, f64),
+}
+
+thread_local! {
+    /// Context of the check running on this thread, set for the duration of
+    /// `start`.
+    static CONTEXT: Cell<*const HcContext> = const { Cell::new(std::ptr::null()) };
+}
+
+/// Makes `context` available to the helpers until the guard is dropped.
+pub struct ContextGuard;
+
+impl ContextGuard {
+    pub fn enter(context: *const HcContext) -> Self {
+        CONTEXT.with(|cell| cell.set(context));
+        ContextGuard
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CONTEXT.with(|cell| cell.set(std::ptr::null()));
+    }
+}
+
+fn with_context<T>(f: impl FnOnce(&HcContext) -> Option<T>) -> Option<T> {
+    let context = CONTEXT.with(|cell| cell.get());
+    // Only set while the host's `start` call, and with it the context, lives.
+    unsafe { context.as_ref() }.and_then(f)
+}
+
+/// Copies a host string, treating null as empty.
+pub unsafe fn read_string(value: *const c_char) -> String {
+    if value.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(value).to_string_lossy().to_string()
+}
+
+pub fn kv_get(key: &str) -> Option<String> {
+    with_context(|context| {
+        let key = CString::new(key).ok()?;
+        let value = unsafe { (context.kv_get)(context.host_data, key.as_ptr()) };
+        if value.is_null() {
+            return None;
+        }
+        Some(unsafe { read_string(value) })
+    })
+}
+
+pub fn kv_set(key: &str, value: &str) -> bool {
+    with_context(|context| {
+        let key = CString::new(key).ok()?;
+        let value = CString::new(value).ok()?;
+        Some(unsafe { (context.kv_set)(context.host_data, key.as_ptr(), value.as_ptr()) } == 0)
+    })
+    .unwrap_or(false)
+}
+
+pub fn log(level: i32, message: &str) {
+    with_context(|context| {
+        let message = CString::new(message).ok()?;
+        unsafe { (context.log)(context.host_data, level, message.as_ptr()) };
+        Some(())
+    });
+}
+
+pub fn emit_metric(name: &str, value: f64) {
+    with_context(|context| {
+        let name = CString::new(name).ok()?;
+        unsafe { (context.emit_metric)(context.host_data, name.as_ptr(), value) };
+        Some(())
+    });
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Version of the JSON result format written by [`CheckResult`].
+const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    /// Working, but degraded.
+    Warning,
+    Critical,
+    /// The check could not tell.
+    Unknown,
+    Crash,
+}
+
+/// Result of a check, written to the host in the JSON result format.
+///
+/// ```ignore
+/// CheckResult::ok()
+///     .message("3/3 pages up")
+///     .metric("pages", 3.0)
+///     .kv("light", "1")
+/// ```
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckResult {
+    version: u32,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+    metrics: HashMap<String, f64>,
+    kv: HashMap<String, String>,
+    tags: Vec<String>,
+}
+
+impl CheckResult {
+    pub fn new(status: Status) -> Self {
+        CheckResult {
+            version: PROTOCOL_VERSION,
+            status,
+            message: None,
+            latency_ms: None,
+            metrics: HashMap::new(),
+            kv: HashMap::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn ok() -> Self {
+        CheckResult::new(Status::Ok)
+    }
+
+    pub fn warning() -> Self {
+        CheckResult::new(Status::Warning)
+    }
+
+    pub fn critical() -> Self {
+        CheckResult::new(Status::Critical)
+    }
+
+    /// Same as [`CheckResult::critical`].
+    pub fn down() -> Self {
+        CheckResult::critical()
+    }
+
+    pub fn unknown() -> Self {
+        CheckResult::new(Status::Unknown)
+    }
+
+    pub fn crash() -> Self {
+        CheckResult::new(Status::Crash)
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn latency_ms(mut self, latency_ms: f64) -> Self {
+        self.latency_ms = Some(latency_ms);
+        self
+    }
+
+    pub fn metric(mut self, name: impl Into<String>, value: f64) -> Self {
+        self.metrics.insert(name.into(), value);
+        self
+    }
+
+    /// Key-value pair persisted by the host, readable later through
+    /// [`crate::kv_get`].
+    pub fn kv(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.kv.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| {
+            format!("{{\"version\":{},\"status\":\"crash\"}}", PROTOCOL_VERSION)
+        })
+    }
+}
+
+impl From<bool> for CheckResult {
+    fn from(healthy: bool) -> Self {
+        if healthy {
+            CheckResult::ok()
+        } else {
+            CheckResult::critical()
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Host functions of the `health_check` import namespace.
+
+use crate::{Request, Response};
+
+mod sys {
+    #[link(wasm_import_module = "health_check")]
+    extern "C" {
+        pub fn kv_get(key_ptr: *const u8, key_len: u32, out_ptr: *mut u8, out_cap: u32) -> i32;
+        pub fn kv_set(
+            key_ptr: *const u8,
+            key_len: u32,
+            value_ptr: *const u8,
+            value_len: u32,
+        ) -> i32;
+        pub fn log(level: i32, ptr: *const u8, len: u32);
+        pub fn emit_metric(name_ptr: *const u8, name_len: u32, value: f64);
+        pub fn now() -> i64;
+        pub fn http_request(request_ptr: *const u8, request_len: u32) -> i32;
+        pub fn http_response(out_ptr: *mut u8, out_cap: u32) -> i32;
+    }
+}
+
+/// Calls a host function that fills a buffer, growing the buffer when the
+/// host reports a larger length. Returns None if the host returned -1.
+fn read_buffer(mut fill: impl FnMut(*mut u8, u32) -> i32) -> Option<String> {
+    let mut buffer = vec![0u8; 256];
+    loop {
+        let len = fill(buffer.as_mut_ptr(), buffer.len() as u32);
+        if len < 0 {
+            return None;
+        }
+
+        let len = len as usize;
+        if len <= buffer.len() {
+            buffer.truncate(len);
+            return String::from_utf8(buffer).ok();
+        }
+        // The value did not fit, the host reported its actual length.
+        buffer.resize(len, 0);
+    }
+}
+
+pub fn kv_get(key: &str) -> Option<String> {
+    read_buffer(|out_ptr, out_cap| unsafe {
+        sys::kv_get(key.as_ptr(), key.len() as u32, out_ptr, out_cap)
+    })
+}
+
+pub fn kv_set(key: &str, value: &str) -> bool {
+    unsafe {
+        sys::kv_set(
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_ptr(),
+            value.len() as u32,
+        ) == 0
+    }
+}
+
+pub fn log(level: i32, message: &str) {
+    unsafe { sys::log(level, message.as_ptr(), message.len() as u32) }
+}
+
+pub fn emit_metric(name: &str, value: f64) {
+    unsafe { sys::emit_metric(name.as_ptr(), name.len() as u32, value) }
+}
+
+pub fn now() -> u64 {
+    unsafe { sys::now() as u64 }
+}
+
+pub fn http_request(request: &Request) -> Option<Response> {
+    let request = serde_json::to_string(request).ok()?;
+    let len = unsafe { sys::http_request(request.as_ptr(), request.len() as u32) };
+    if len < 0 {
+        return None;
+    }
+
+    let response = read_buffer(|out_ptr, out_cap| unsafe { sys::http_response(out_ptr, out_cap) })?;
+    serde_json::from_str(&response).ok()
+}
@@ -0,0 +1,58 @@
+/*
+ * C ABI for native health-check modules.
+ *
+ * A library opts into this ABI by exporting
+ *
+ *     uint32_t health_check_abi_version(void) { return HEALTH_CHECK_ABI_VERSION; }
+ *     const char *start(const hc_context *ctx);
+ *     void free_string(const char *result);
+ *
+ * `start` returns the check result in the result protocol (JSON or the
+ * legacy line format); the host hands it back to `free_string` once read.
+ *
+ * Libraries without `health_check_abi_version` are driven with ABI 1, where
+ * workers export `const char *start(void)` and runners export
+ * `const char *start(const char *env)`. An ABI 1 runner that also exports
+ * the symbol `health_check_runner_payload` (of any type) is called as
+ * `const char *start(const char *env, const char *payload)` instead.
+ */
+#ifndef HEALTH_CHECK_H
+#define HEALTH_CHECK_H
+
+#include <stdint.h>
+
+#define HEALTH_CHECK_ABI_VERSION 2
+
+#define HC_LOG_DEBUG 0
+#define HC_LOG_INFO 1
+#define HC_LOG_WARN 2
+#define HC_LOG_ERROR 3
+
+/*
+ * Everything the host passes to `start`. All strings are NUL-terminated,
+ * owned by the host and only valid until `start` returns; the same holds for
+ * strings returned by `kv_get`. The callbacks may be called from any thread
+ * while `start` runs and must always be given `host_data` back.
+ */
+typedef struct hc_context {
+    uint32_t abi_version;
+    /* File name of the module, e.g. "weather_run.so". */
+    const char *module_name;
+    /* JSON object of the module's `settings` from the host configuration. */
+    const char *config;
+    /* JSON run payload for runners, empty for workers and plain triggers. */
+    const char *payload;
+    /* Environment as KEY=VALUE;;; pairs, including payload env entries. */
+    const char *env;
+    /* Unix time in milliseconds by which the module should have returned. */
+    uint64_t deadline_unix_ms;
+    void *host_data;
+    void (*log)(void *host_data, int32_t level, const char *message);
+    /* Returns NULL when the key has no value. */
+    const char *(*kv_get)(void *host_data, const char *key);
+    /* Returns 0 on success. */
+    int32_t (*kv_set)(void *host_data, const char *key, const char *value);
+    void (*emit_metric)(void *host_data, const char *name, double value);
+} hc_context;
+
+#endif
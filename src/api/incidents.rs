@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{persistency::Incident, threads::unix_timestamp};
+
+use super::AppState;
+
+/// Incidents returned when no `limit` is given.
+const DEFAULT_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct IncidentsQuery {
+    module: Option<String>,
+    #[serde(default)]
+    open: bool,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct AcknowledgeQuery {
+    by: Option<String>,
+}
+
+/// Lists the most recent incidents, optionally only those of one `module` or,
+/// with `open=true`, only those not resolved yet.
+pub async fn get_incidents(
+    Query(query): Query<IncidentsQuery>,
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> (StatusCode, HeaderMap, String) {
+    let connection = match state.lock() {
+        Ok(val) => val.connection.clone(),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+    let connection = match connection.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    match Incident::list(
+        &connection,
+        query.module.as_deref(),
+        query.open,
+        query.limit.unwrap_or(DEFAULT_LIMIT),
+    ) {
+        Ok(incidents) => json_response(&incidents),
+        Err(err) => {
+            eprintln!("Error: Could not list incidents: {}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error listing incidents".to_string(),
+            )
+        }
+    }
+}
+
+/// Acknowledges an incident, optionally recording who did it with `by`.
+/// Acknowledging it again keeps the first acknowledgement.
+pub async fn acknowledge_incident(
+    Path(id): Path<i64>,
+    Query(query): Query<AcknowledgeQuery>,
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> (StatusCode, HeaderMap, String) {
+    let connection = match state.lock() {
+        Ok(val) => val.connection.clone(),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+    let connection = match connection.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    match Incident::acknowledge(&connection, id, unix_timestamp(), query.by.as_deref()) {
+        Ok(Some(incident)) => json_response(&incident),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            HeaderMap::new(),
+            "Incident not found".to_string(),
+        ),
+        Err(err) => {
+            eprintln!("Error: Could not acknowledge incident {}: {}", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error acknowledging incident".to_string(),
+            )
+        }
+    }
+}
+
+fn json_response(value: &impl Serialize) -> (StatusCode, HeaderMap, String) {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    match serde_json::to_string(value) {
+        Ok(body) => (StatusCode::OK, headers, body),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            HeaderMap::new(),
+            "Error serializing incidents".to_string(),
+        ),
+    }
+}
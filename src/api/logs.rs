@@ -0,0 +1,105 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::logs::{LogLine, ModuleLogs};
+
+use super::AppState;
+
+/// Lines returned when no `tail` is given.
+const DEFAULT_TAIL: usize = 100;
+
+/// How often a followed log is checked for new lines.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Deserialize)]
+pub struct LogsQuery {
+    tail: Option<usize>,
+    #[serde(default)]
+    follow: bool,
+}
+
+/// Returns the most recent output lines of a module. With `follow=true` the
+/// connection is kept open and new lines are streamed as they arrive.
+pub async fn get_module_logs(
+    Path(module_name): Path<String>,
+    Query(query): Query<LogsQuery>,
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> Response {
+    let module_logs = match state.lock() {
+        Ok(val) => val.module_logs.clone(),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting lock".to_string(),
+            )
+                .into_response();
+        }
+    };
+
+    let tail = query.tail.unwrap_or(DEFAULT_TAIL);
+    let Some((lines, next_seq)) = module_logs.read(&module_name, |buffer| {
+        (buffer.tail(tail), buffer.next_seq())
+    }) else {
+        return (StatusCode::NOT_FOUND, "Module not found".to_string()).into_response();
+    };
+
+    if !query.follow {
+        return (StatusCode::OK, render_lines(&lines)).into_response();
+    }
+
+    let initial = render_lines(&lines);
+    let stream = futures_util::stream::unfold(
+        (module_logs, module_name, next_seq, Some(initial)),
+        follow_logs,
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from_stream(stream))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+type FollowState = (ModuleLogs, String, u64, Option<String>);
+
+async fn follow_logs(
+    (module_logs, module_name, mut next_seq, initial): FollowState,
+) -> Option<(Result<String, Infallible>, FollowState)> {
+    if let Some(initial) = initial {
+        return Some((Ok(initial), (module_logs, module_name, next_seq, None)));
+    }
+
+    loop {
+        let (lines, seq) = module_logs.read(&module_name, |buffer| {
+            (buffer.since(next_seq), buffer.next_seq())
+        })?;
+        next_seq = seq;
+
+        if !lines.is_empty() {
+            let chunk = render_lines(&lines);
+            return Some((Ok(chunk), (module_logs, module_name, next_seq, None)));
+        }
+
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+    }
+}
+
+fn render_lines(lines: &[LogLine]) -> String {
+    let mut body = String::new();
+    for line in lines {
+        body.push_str(&line.to_string());
+        body.push('\n');
+    }
+    body
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+
+use crate::{
+    protocol::{ModuleOutput, ResultStatus},
+    threads::unix_timestamp,
+    types::LatencyStats,
+    uptime::{self, UptimeReport},
+};
+
+use super::AppState;
+
+/// Exposes the module states and the metrics reported by modules in the
+/// Prometheus text format.
+pub async fn get_metrics(
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> (StatusCode, HeaderMap, String) {
+    let state = match state.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    let mut statuses = Vec::new();
+    let mut latencies = Vec::new();
+    let mut outputs = Vec::new();
+
+    if let Ok(worker_states) = state.worker_states.lock() {
+        for (name, worker_state) in worker_states.iter() {
+            statuses.push((name.clone(), "wasm_worker", worker_state.status));
+            latencies.push((name.clone(), "wasm_worker", worker_state.latency.clone()));
+            outputs.push((name.clone(), worker_state.last_output.clone()));
+        }
+    }
+    if let Ok(native_worker_states) = state.native_worker_states.lock() {
+        for (name, native_worker_state) in native_worker_states.iter() {
+            statuses.push((name.clone(), "native_worker", native_worker_state.status));
+            latencies.push((
+                name.clone(),
+                "native_worker",
+                native_worker_state.latency.clone(),
+            ));
+            outputs.push((name.clone(), native_worker_state.last_output.clone()));
+        }
+    }
+    if let Ok(runner_states) = state.runner_states.lock() {
+        for (name, runner_state) in runner_states.iter() {
+            statuses.push((name.clone(), "wasm_runner", runner_state.status));
+            latencies.push((name.clone(), "wasm_runner", runner_state.latency.clone()));
+            outputs.push((name.clone(), runner_state.last_output.clone()));
+        }
+    }
+    if let Ok(native_states) = state.native_states.lock() {
+        for (name, native_state) in native_states.iter() {
+            statuses.push((name.clone(), "native_runner", native_state.status));
+            latencies.push((name.clone(), "native_runner", native_state.latency.clone()));
+            outputs.push((name.clone(), native_state.last_output.clone()));
+        }
+    }
+
+    let mut body = String::new();
+
+    body.push_str("# TYPE health_check_up gauge\n");
+    for (name, kind, status) in statuses.iter() {
+        let _ = writeln!(
+            body,
+            "health_check_up{{module=\"{}\",kind=\"{}\"}} {}",
+            escape_label(name),
+            kind,
+            status.is_up() as u8
+        );
+    }
+
+    // One series per possible status so alerts can match on the label.
+    body.push_str("# TYPE health_check_status gauge\n");
+    for (name, kind, status) in statuses.iter() {
+        for candidate in ResultStatus::ALL {
+            let _ = writeln!(
+                body,
+                "health_check_status{{module=\"{}\",kind=\"{}\",status=\"{}\"}} {}",
+                escape_label(name),
+                kind,
+                candidate.as_str(),
+                (*status == candidate) as u8
+            );
+        }
+    }
+
+    body.push_str("# TYPE health_check_execution_duration_ms summary\n");
+    for (name, kind, latency) in latencies.iter() {
+        write_latency(&mut body, name, kind, latency);
+    }
+
+    body.push_str("# TYPE health_check_reported_latency_ms gauge\n");
+    for (name, output) in outputs.iter() {
+        if let Some(latency_ms) = output.as_ref().and_then(|output| output.latency_ms) {
+            let _ = writeln!(
+                body,
+                "health_check_reported_latency_ms{{module=\"{}\"}} {}",
+                escape_label(name),
+                latency_ms
+            );
+        }
+    }
+
+    let mut reports = Vec::new();
+    if let Ok(connection) = state.connection.lock() {
+        let now = unix_timestamp();
+        for (name, kind, _) in statuses.iter() {
+            if !kind.ends_with("worker") {
+                continue;
+            }
+            let slo_target_percent = state.config.module(name).slo_target_percent;
+            match uptime::report(
+                &connection,
+                name,
+                &state.config.uptime_windows,
+                slo_target_percent,
+                now,
+            ) {
+                Ok(val) => reports.push(val),
+                Err(err) => eprintln!("Error: Could not compute uptime of {}: {}", name, err),
+            }
+        }
+    }
+    write_uptime(&mut body, &reports);
+
+    body.push_str("# TYPE health_check_module_metric gauge\n");
+    for (name, output) in outputs.iter() {
+        let Some(ModuleOutput { metrics, .. }) = output else {
+            continue;
+        };
+        for (metric, value) in metrics.iter() {
+            let _ = writeln!(
+                body,
+                "health_check_module_metric{{module=\"{}\",name=\"{}\"}} {}",
+                escape_label(name),
+                escape_label(metric),
+                value
+            );
+        }
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+
+    (StatusCode::OK, headers, body)
+}
+
+fn write_latency(body: &mut String, name: &str, kind: &str, latency: &LatencyStats) {
+    let labels = format!("module=\"{}\",kind=\"{}\"", escape_label(name), kind);
+    for quantile in [0.5, 0.9, 0.99] {
+        if let Some(value) = latency.percentile(quantile) {
+            let _ = writeln!(
+                body,
+                "health_check_execution_duration_ms{{{},quantile=\"{}\"}} {}",
+                labels, quantile, value
+            );
+        }
+    }
+    let _ = writeln!(
+        body,
+        "health_check_execution_duration_ms_sum{{{}}} {}",
+        labels, latency.sum_ms
+    );
+    let _ = writeln!(
+        body,
+        "health_check_execution_duration_ms_count{{{}}} {}",
+        labels, latency.count
+    );
+}
+
+fn write_uptime(body: &mut String, reports: &[UptimeReport]) {
+    body.push_str("# TYPE health_check_uptime_percent gauge\n");
+    for report in reports.iter() {
+        for window in report.windows.iter() {
+            if let Some(uptime_percent) = window.uptime_percent {
+                let _ = writeln!(
+                    body,
+                    "health_check_uptime_percent{{module=\"{}\",window=\"{}\"}} {}",
+                    escape_label(&report.module),
+                    window.window,
+                    uptime_percent
+                );
+            }
+        }
+    }
+
+    body.push_str("# TYPE health_check_slo_burn_rate gauge\n");
+    for report in reports.iter() {
+        for window in report.windows.iter() {
+            if let Some(burn_rate) = window.burn_rate {
+                let _ = writeln!(
+                    body,
+                    "health_check_slo_burn_rate{{module=\"{}\",window=\"{}\"}} {}",
+                    escape_label(&report.module),
+                    window.window,
+                    burn_rate
+                );
+            }
+        }
+    }
+
+    body.push_str("# TYPE health_check_error_budget_remaining_percent gauge\n");
+    for report in reports.iter() {
+        if let Some(remaining) = report.error_budget_remaining_percent {
+            let _ = writeln!(
+                body,
+                "health_check_error_budget_remaining_percent{{module=\"{}\"}} {}",
+                escape_label(&report.module),
+                remaining
+            );
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
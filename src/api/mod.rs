@@ -4,45 +4,80 @@ use std::{
 };
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     routing::{get, post},
     Router,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::types::{NativeStates, NativeWorkerStates, RunnerState, WorkerStates};
+use sqlite::Connection;
+
+use crate::{
+    config::{Config, StatusCodes},
+    logs::ModuleLogs,
+    protocol::{ModuleOutput, ResultStatus},
+    types::{LatencySummary, RunnerState, WorkerStates},
+};
+
+mod incidents;
+mod logs;
+mod metrics;
+mod modules;
+mod thunder;
 
 struct AppState {
     worker_states: Arc<Mutex<HashMap<String, WorkerStates>>>,
-    native_worker_states: Arc<Mutex<HashMap<String, NativeWorkerStates>>>,
+    native_worker_states: Arc<Mutex<HashMap<String, WorkerStates>>>,
     runner_states: Arc<Mutex<HashMap<String, RunnerState>>>,
-    native_states: Arc<Mutex<HashMap<String, NativeStates>>>,
+    native_states: Arc<Mutex<HashMap<String, RunnerState>>>,
+    connection: Arc<Mutex<Connection>>,
+    module_logs: ModuleLogs,
+    config: Config,
 }
 
 #[tokio::main]
 pub async fn create_server(
     worker_states: Arc<Mutex<HashMap<String, WorkerStates>>>,
-    native_worker_states: Arc<Mutex<HashMap<String, NativeWorkerStates>>>,
+    native_worker_states: Arc<Mutex<HashMap<String, WorkerStates>>>,
     runner_states: Arc<Mutex<HashMap<String, RunnerState>>>,
-    native_states: Arc<Mutex<HashMap<String, NativeStates>>>,
+    native_states: Arc<Mutex<HashMap<String, RunnerState>>>,
+    connection: Arc<Mutex<Connection>>,
+    module_logs: ModuleLogs,
+    config: Config,
 ) {
     let app_state = AppState {
         worker_states,
         native_worker_states,
         runner_states,
         native_states,
+        connection,
+        module_logs,
+        config,
     };
     // build our application with a single route
     let app = Router::new()
         .route("/health/:service_name", get(get_health))
         .route("/health/lib/:service_name", get(get_lib_health))
-        .route("/thunder/lib/:service_name", post(run_lib_service_thunder))
-        .route("/thunder/:service_name", post(run_service_thunder))
+        .route(
+            "/thunder/lib/:service_name",
+            post(thunder::run_lib_service_thunder),
+        )
+        .route("/thunder/:service_name", post(thunder::run_service_thunder))
         .route("/thunder/stats/:service_name", get(get_service_stats))
         .route(
             "/thunder/stats/lib/:service_name",
             get(get_lib_service_stats),
         )
+        .route("/modules", get(modules::get_modules))
+        .route("/modules/:service_name/logs", get(logs::get_module_logs))
+        .route(
+            "/modules/:service_name/uptime",
+            get(modules::get_module_uptime),
+        )
+        .route("/incidents", get(incidents::get_incidents))
+        .route("/incidents/:id/ack", post(incidents::acknowledge_incident))
+        .route("/metrics", get(metrics::get_metrics))
         .with_state(Arc::new(Mutex::new(app_state)));
 
     // run our app with hyper, listening globally on port 3000
@@ -50,15 +85,80 @@ pub async fn create_server(
     axum::serve(listener, app).await.unwrap();
 }
 
+#[derive(Deserialize)]
+struct HealthQuery {
+    format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HealthReport<'a> {
+    service: &'a str,
+    status: ResultStatus,
+    exit_code: Option<i32>,
+    crash_reason: Option<&'a str>,
+    restarts: u32,
+    latency: LatencySummary,
+    output: Option<&'a ModuleOutput>,
+}
+
+/// Status code and `x-health-status` header for a status, following the
+/// configured [`StatusCodes`].
+fn status_response(status_codes: &StatusCodes, status: ResultStatus) -> (StatusCode, HeaderMap) {
+    let status_code = StatusCode::from_u16(status_codes.code(status))
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut headers = HeaderMap::new();
+    headers.insert("x-health-status", HeaderValue::from_static(status.as_str()));
+    (status_code, headers)
+}
+
+fn health_message(status: ResultStatus) -> &'static str {
+    match status {
+        ResultStatus::Ok => "OK",
+        ResultStatus::Warning => "Service is degraded",
+        ResultStatus::Critical => "Service is not available",
+        ResultStatus::Unknown => "Service status is unknown",
+        ResultStatus::Crash => "Health service is not available",
+    }
+}
+
+/// Renders a health answer either as the plain text message or, when the
+/// request asked for `format=json`, as a [`HealthReport`].
+fn health_response(
+    query: &HealthQuery,
+    status_code: StatusCode,
+    mut headers: HeaderMap,
+    message: &str,
+    report: HealthReport,
+) -> (StatusCode, HeaderMap, String) {
+    if query.format.as_deref() != Some("json") {
+        return (status_code, headers, message.to_string());
+    }
+
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    match serde_json::to_string(&report) {
+        Ok(body) => (status_code, headers, body),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            HeaderMap::new(),
+            "Error serializing health report".to_string(),
+        ),
+    }
+}
+
 async fn get_health(
     Path(service_name): Path<String>,
+    Query(query): Query<HealthQuery>,
     State(state): State<Arc<Mutex<AppState>>>,
-) -> (StatusCode, String) {
+) -> (StatusCode, HeaderMap, String) {
     let state = match state.lock() {
         Ok(val) => val,
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
                 "Error getting lock".to_string(),
             );
         }
@@ -69,6 +169,7 @@ async fn get_health(
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
                 "Error getting lock".to_string(),
             );
         }
@@ -78,33 +179,47 @@ async fn get_health(
     // println!("State: {:?}", state);
 
     if let Some(worker_state) = worker_states.get(&service_name) {
-        if worker_state.on_crash {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Health service is not available".to_string(),
-            );
-        } else if worker_state.alive {
-            return (StatusCode::OK, "OK".to_string());
-        } else {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Service is not available".to_string(),
-            );
+        let (status_code, mut headers) =
+            status_response(&state.config.status_codes, worker_state.status);
+        if let Some(exit_code) = worker_state.exit_code {
+            headers.insert("x-exit-code", HeaderValue::from(exit_code));
         }
+
+        return health_response(
+            &query,
+            status_code,
+            headers,
+            health_message(worker_state.status),
+            HealthReport {
+                service: &service_name,
+                status: worker_state.status,
+                exit_code: worker_state.exit_code,
+                crash_reason: worker_state.crash_reason.as_deref(),
+                restarts: worker_state.restarts,
+                latency: worker_state.latency.summary(),
+                output: worker_state.last_output.as_ref(),
+            },
+        );
     } else {
-        return (StatusCode::NOT_FOUND, "Service not found".to_string());
+        return (
+            StatusCode::NOT_FOUND,
+            HeaderMap::new(),
+            "Service not found".to_string(),
+        );
     };
 }
 
 async fn get_lib_health(
     Path(service_name): Path<String>,
+    Query(query): Query<HealthQuery>,
     State(state): State<Arc<Mutex<AppState>>>,
-) -> (StatusCode, String) {
+) -> (StatusCode, HeaderMap, String) {
     let state = match state.lock() {
         Ok(val) => val,
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
                 "Error getting lock".to_string(),
             );
         }
@@ -115,116 +230,38 @@ async fn get_lib_health(
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
                 "Error getting lock".to_string(),
             );
         }
     };
 
     if let Some(native_worker_state) = native_worker_states.get(&service_name) {
-        if native_worker_state.on_crash {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Health service is not available".to_string(),
-            );
-        } else if native_worker_state.alive {
-            return (StatusCode::OK, "OK".to_string());
-        } else {
-            return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Service is not available".to_string(),
-            );
-        }
-    } else {
-        return (StatusCode::NOT_FOUND, "Service not found".to_string());
-    };
-}
+        let (status_code, headers) =
+            status_response(&state.config.status_codes, native_worker_state.status);
 
-async fn run_service_thunder(
-    Path(service_name): Path<String>,
-    State(state): State<Arc<Mutex<AppState>>>,
-) -> (StatusCode, String) {
-    let state = match state.lock() {
-        Ok(val) => val,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Error getting lock".to_string(),
-            );
-        }
-    };
-
-    let runner_state = match state.runner_states.lock() {
-        Ok(val) => val,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Error getting lock".to_string(),
-            );
-        }
-    };
-
-    // Schedule a run for the service.
-
-    if let Some(runner_state) = runner_state.get(&service_name) {
-        // Check if the service is already running.
-        match runner_state.channel_trigger.send(()) {
-            Ok(_) => {
-                return (StatusCode::OK, "Service is running".to_string());
-            }
-            Err(_) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Error sending trigger to service".to_string(),
-                );
-            }
-        }
+        return health_response(
+            &query,
+            status_code,
+            headers,
+            health_message(native_worker_state.status),
+            HealthReport {
+                service: &service_name,
+                status: native_worker_state.status,
+                exit_code: None,
+                crash_reason: native_worker_state.crash_reason.as_deref(),
+                restarts: native_worker_state.restarts,
+                latency: native_worker_state.latency.summary(),
+                output: native_worker_state.last_output.as_ref(),
+            },
+        );
     } else {
-        return (StatusCode::NOT_FOUND, "Service not found".to_string());
-    }
-}
-
-async fn run_lib_service_thunder(
-    Path(service_name): Path<String>,
-    State(state): State<Arc<Mutex<AppState>>>,
-) -> (StatusCode, String) {
-    let state = match state.lock() {
-        Ok(val) => val,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Error getting lock".to_string(),
-            );
-        }
-    };
-
-    let native_state = match state.native_states.lock() {
-        Ok(val) => val,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Error getting lock".to_string(),
-            );
-        }
+        return (
+            StatusCode::NOT_FOUND,
+            HeaderMap::new(),
+            "Service not found".to_string(),
+        );
     };
-
-    // Schedule a run for the service.
-
-    if let Some(native_state) = native_state.get(&service_name) {
-        // Check if the service is already running.
-        match native_state.channel_trigger.send(()) {
-            Ok(_) => {
-                return (StatusCode::OK, "Service is running".to_string());
-            }
-            Err(_) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Error sending trigger to service".to_string(),
-                );
-            }
-        }
-    } else {
-        return (StatusCode::NOT_FOUND, "Service not found".to_string());
-    }
 }
 
 async fn get_service_stats(
@@ -252,11 +289,20 @@ async fn get_service_stats(
     };
 
     if let Some(runner_state) = runner_state.get(&service_name) {
+        let (queue_depth, in_flight) = runner_state.queue.depth();
         return (
             StatusCode::OK,
             format!(
-                "Service: {}\nLast run: {:?}\nLast run success: {}\n",
-                runner_state.module_name, runner_state.last_run, runner_state.last_run_success
+                "Service: {}\nLast run: {:?}\nStatus: {}\nLast message: {}\nQueue depth: {}\nIn flight: {}\nCrash reason: {}\nRestarts: {}\nLatency: {}\n",
+                runner_state.module_name,
+                runner_state.last_run,
+                runner_state.status.as_str(),
+                last_message(&runner_state.last_output),
+                queue_depth,
+                in_flight,
+                runner_state.crash_reason.as_deref().unwrap_or("-"),
+                runner_state.restarts,
+                latency_line(&runner_state.latency.summary())
             ),
         );
     } else {
@@ -289,17 +335,45 @@ async fn get_lib_service_stats(
     };
 
     if let Some(native_state) = native_state.get(&service_name) {
+        let (queue_depth, in_flight) = native_state.queue.depth();
         return (
             StatusCode::OK,
             format!(
-                "Service: {}\nLast run: {:?}\nLast run success: {}\nOn Crash: {}\n",
+                "Service: {}\nLast run: {:?}\nStatus: {}\nLast message: {}\nQueue depth: {}\nIn flight: {}\nCrash reason: {}\nRestarts: {}\nLatency: {}\n",
                 native_state.module_name,
                 native_state.last_run,
-                native_state.last_run_success,
-                native_state.on_crash
+                native_state.status.as_str(),
+                last_message(&native_state.last_output),
+                queue_depth,
+                in_flight,
+                native_state.crash_reason.as_deref().unwrap_or("-"),
+                native_state.restarts,
+                latency_line(&native_state.latency.summary())
             ),
         );
     } else {
         return (StatusCode::NOT_FOUND, "Service not found".to_string());
     }
 }
+
+fn latency_line(summary: &LatencySummary) -> String {
+    let format = |value: Option<f64>| match value {
+        Some(val) => format!("{:.1}ms", val),
+        None => "-".to_string(),
+    };
+    format!(
+        "last {} p50 {} p90 {} p99 {} over {} runs",
+        format(summary.last_ms),
+        format(summary.p50_ms),
+        format(summary.p90_ms),
+        format(summary.p99_ms),
+        summary.count
+    )
+}
+
+fn last_message(last_output: &Option<ModuleOutput>) -> &str {
+    last_output
+        .as_ref()
+        .and_then(|output| output.message.as_deref())
+        .unwrap_or("-")
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use serde::Serialize;
+
+use crate::{protocol::ResultStatus, threads::unix_timestamp, types::LatencySummary, uptime};
+
+use super::AppState;
+
+#[derive(Serialize)]
+struct ModuleEntry {
+    name: String,
+    kind: &'static str,
+    status: ResultStatus,
+    healthy: bool,
+    load_error: Option<String>,
+    crash_reason: Option<String>,
+    restarts: u32,
+    latency: LatencySummary,
+}
+
+/// Lists every known module with its kind and, for modules that failed to
+/// load, the precise reason.
+pub async fn get_modules(
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> (StatusCode, HeaderMap, String) {
+    let state = match state.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    let mut modules = Vec::new();
+
+    for (kind, worker_states) in [
+        ("wasm_worker", &state.worker_states),
+        ("native_worker", &state.native_worker_states),
+    ] {
+        if let Ok(worker_states) = worker_states.lock() {
+            for (name, worker_state) in worker_states.iter() {
+                modules.push(ModuleEntry {
+                    name: name.clone(),
+                    kind,
+                    status: worker_state.status,
+                    healthy: worker_state.status.is_up(),
+                    load_error: worker_state.load_error.clone(),
+                    crash_reason: worker_state.crash_reason.clone(),
+                    restarts: worker_state.restarts,
+                    latency: worker_state.latency.summary(),
+                });
+            }
+        }
+    }
+
+    for (kind, runner_states) in [
+        ("wasm_runner", &state.runner_states),
+        ("native_runner", &state.native_states),
+    ] {
+        if let Ok(runner_states) = runner_states.lock() {
+            for (name, runner_state) in runner_states.iter() {
+                modules.push(ModuleEntry {
+                    name: name.clone(),
+                    kind,
+                    status: runner_state.status,
+                    healthy: runner_state.status.is_up(),
+                    load_error: runner_state.load_error.clone(),
+                    crash_reason: runner_state.crash_reason.clone(),
+                    restarts: runner_state.restarts,
+                    latency: runner_state.latency.summary(),
+                });
+            }
+        }
+    }
+
+    modules.sort_by(|left, right| left.name.cmp(&right.name));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    match serde_json::to_string(&modules) {
+        Ok(body) => (StatusCode::OK, headers, body),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            HeaderMap::new(),
+            "Error serializing module list".to_string(),
+        ),
+    }
+}
+
+/// Reports the uptime of a worker over the configured windows and, when the
+/// module has an SLO target, how fast its error budget burns.
+pub async fn get_module_uptime(
+    Path(service_name): Path<String>,
+    State(state): State<Arc<Mutex<AppState>>>,
+) -> (StatusCode, HeaderMap, String) {
+    let state = match state.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    let is_worker = [&state.worker_states, &state.native_worker_states]
+        .iter()
+        .any(|worker_states| match worker_states.lock() {
+            Ok(val) => val.contains_key(&service_name),
+            Err(_) => false,
+        });
+    if !is_worker {
+        return (
+            StatusCode::NOT_FOUND,
+            HeaderMap::new(),
+            "Service not found".to_string(),
+        );
+    }
+
+    let connection = match state.connection.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    let report = match uptime::report(
+        &connection,
+        &service_name,
+        &state.config.uptime_windows,
+        state.config.module(&service_name).slo_target_percent,
+        unix_timestamp(),
+    ) {
+        Ok(val) => val,
+        Err(err) => {
+            eprintln!(
+                "Error: Could not compute uptime of {}: {}",
+                service_name, err
+            );
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error computing uptime".to_string(),
+            );
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    match serde_json::to_string(&report) {
+        Ok(body) => (StatusCode::OK, headers, body),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            HeaderMap::new(),
+            "Error serializing uptime report".to_string(),
+        ),
+    }
+}
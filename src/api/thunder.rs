@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use serde::Deserialize;
+use tokio::sync::oneshot;
+
+use crate::{
+    config::StatusCodes,
+    threads::EnqueueError,
+    types::{RunOutcome, RunPayload, RunnerState, Trigger},
+};
+
+use super::{status_response, AppState};
+
+/// Seconds a `wait=true` request blocks when no `timeout` is given.
+const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Deserialize)]
+pub struct ThunderQuery {
+    #[serde(default)]
+    wait: bool,
+    timeout: Option<u64>,
+}
+
+pub async fn run_service_thunder(
+    Path(service_name): Path<String>,
+    Query(query): Query<ThunderQuery>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    body: Bytes,
+) -> (StatusCode, HeaderMap, String) {
+    let (runner_states, status_codes) = match state.lock() {
+        Ok(val) => (val.runner_states.clone(), val.config.status_codes.clone()),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    run_thunder(&runner_states, &status_codes, &service_name, &query, &body).await
+}
+
+pub async fn run_lib_service_thunder(
+    Path(service_name): Path<String>,
+    Query(query): Query<ThunderQuery>,
+    State(state): State<Arc<Mutex<AppState>>>,
+    body: Bytes,
+) -> (StatusCode, HeaderMap, String) {
+    let (native_states, status_codes) = match state.lock() {
+        Ok(val) => (val.native_states.clone(), val.config.status_codes.clone()),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                HeaderMap::new(),
+                "Error getting lock".to_string(),
+            );
+        }
+    };
+
+    run_thunder(&native_states, &status_codes, &service_name, &query, &body).await
+}
+
+/// Schedules a run for the service and, with `wait=true`, blocks until the
+/// runner reports the outcome or the timeout expires. A non-empty body is
+/// parsed as the [`RunPayload`] handed to the runner.
+async fn run_thunder(
+    runner_states: &Arc<Mutex<HashMap<String, RunnerState>>>,
+    status_codes: &StatusCodes,
+    service_name: &str,
+    query: &ThunderQuery,
+    body: &Bytes,
+) -> (StatusCode, HeaderMap, String) {
+    let payload = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice::<RunPayload>(body) {
+            Ok(val) => Some(val),
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    HeaderMap::new(),
+                    format!("Invalid payload: {}", err),
+                );
+            }
+        }
+    };
+
+    let reply_receiver = match schedule_run(runner_states, service_name, payload, query.wait) {
+        Ok(val) => val,
+        Err((status_code, message)) => return (status_code, HeaderMap::new(), message),
+    };
+
+    let Some(reply_receiver) = reply_receiver else {
+        return (
+            StatusCode::OK,
+            HeaderMap::new(),
+            "Service is running".to_string(),
+        );
+    };
+
+    let timeout = query.timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT_SECS);
+    match tokio::time::timeout(Duration::from_secs(timeout), reply_receiver).await {
+        Ok(Ok(outcome)) => outcome_response(status_codes, &outcome),
+        Ok(Err(_)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            HeaderMap::new(),
+            "Runner stopped before the run finished".to_string(),
+        ),
+        Err(_) => (
+            StatusCode::GATEWAY_TIMEOUT,
+            HeaderMap::new(),
+            format!("Run did not finish within {} seconds", timeout),
+        ),
+    }
+}
+
+fn schedule_run(
+    runner_states: &Arc<Mutex<HashMap<String, RunnerState>>>,
+    service_name: &str,
+    payload: Option<RunPayload>,
+    wait: bool,
+) -> Result<Option<oneshot::Receiver<RunOutcome>>, (StatusCode, String)> {
+    let runner_states = match runner_states.lock() {
+        Ok(val) => val,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error getting lock".to_string(),
+            ));
+        }
+    };
+
+    let Some(runner_state) = runner_states.get(service_name) else {
+        return Err((StatusCode::NOT_FOUND, "Service not found".to_string()));
+    };
+
+    let (reply, reply_receiver) = if wait {
+        let (reply, reply_receiver) = oneshot::channel();
+        (Some(reply), Some(reply_receiver))
+    } else {
+        (None, None)
+    };
+
+    match runner_state.queue.push(Trigger { payload, reply }) {
+        Ok(_) => Ok(reply_receiver),
+        Err(EnqueueError::Full) => Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Run queue of service is full".to_string(),
+        )),
+        Err(EnqueueError::Closed) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error sending trigger to service".to_string(),
+        )),
+    }
+}
+
+fn outcome_response(
+    status_codes: &StatusCodes,
+    outcome: &RunOutcome,
+) -> (StatusCode, HeaderMap, String) {
+    let (status_code, mut headers) = status_response(status_codes, outcome.status);
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    match serde_json::to_string(outcome) {
+        Ok(body) => (status_code, headers, body),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            HeaderMap::new(),
+            "Error serializing run outcome".to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::RunnerConfig, protocol::ResultStatus, threads::RunQueue, types::LatencyStats,
+    };
+
+    use super::*;
+
+    fn runner_states(max_queue: Option<usize>) -> Arc<Mutex<HashMap<String, RunnerState>>> {
+        let queue = Arc::new(RunQueue::new(RunnerConfig {
+            max_queue,
+            ..Default::default()
+        }));
+        queue.attach();
+
+        let runner_state = RunnerState {
+            module_name: "runner".to_string(),
+            status: ResultStatus::Unknown,
+            last_run: std::time::Instant::now(),
+            last_output: None,
+            load_error: None,
+            crash_reason: None,
+            restarts: 0,
+            latency: LatencyStats::default(),
+            queue,
+        };
+        Arc::new(Mutex::new(HashMap::from([(
+            "runner".to_string(),
+            runner_state,
+        )])))
+    }
+
+    #[test]
+    fn full_queue_answers_too_many_requests() {
+        let runner_states = runner_states(Some(1));
+
+        assert!(schedule_run(&runner_states, "runner", None, false).is_ok());
+        let Err((status_code, _)) = schedule_run(&runner_states, "runner", None, false) else {
+            panic!("Second trigger was accepted");
+        };
+        assert_eq!(status_code, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn unknown_service_is_not_found() {
+        let runner_states = runner_states(None);
+
+        let Err((status_code, _)) = schedule_run(&runner_states, "missing", None, false) else {
+            panic!("Trigger for a missing service was accepted");
+        };
+        assert_eq!(status_code, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn waiting_trigger_gets_a_receiver() {
+        let runner_states = runner_states(None);
+
+        let reply_receiver = schedule_run(&runner_states, "runner", None, true).unwrap();
+        assert!(reply_receiver.is_some());
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::protocol::ModuleOutput;
+
+/// Opens and closes an AMQP 0-9-1 connection with PLAIN credentials.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AmqpCheck {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_credential")]
+    pub username: String,
+    #[serde(default = "default_credential")]
+    pub password: String,
+    #[serde(default = "default_vhost")]
+    pub vhost: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_port() -> u16 {
+    5672
+}
+
+fn default_credential() -> String {
+    "guest".to_string()
+}
+
+fn default_vhost() -> String {
+    "/".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+const FRAME_METHOD: u8 = 1;
+const FRAME_END: u8 = 0xCE;
+/// Largest frame accepted before the broker and client agreed on one; the
+/// handshake methods are far smaller.
+const MAX_FRAME_SIZE: usize = 128 * 1024;
+
+const CONNECTION_START: (u16, u16) = (10, 10);
+const CONNECTION_START_OK: (u16, u16) = (10, 11);
+const CONNECTION_TUNE: (u16, u16) = (10, 30);
+const CONNECTION_TUNE_OK: (u16, u16) = (10, 31);
+const CONNECTION_OPEN: (u16, u16) = (10, 40);
+const CONNECTION_OPEN_OK: (u16, u16) = (10, 41);
+const CONNECTION_CLOSE: (u16, u16) = (10, 50);
+
+impl AmqpCheck {
+    pub fn run(&self) -> ModuleOutput {
+        super::probe_output(|| self.probe())
+    }
+
+    fn probe(&self) -> Result<String, String> {
+        let mut stream = super::connect(
+            &self.host,
+            self.port,
+            Duration::from_secs(self.timeout_secs),
+        )?;
+        stream
+            .write_all(b"AMQP\x00\x00\x09\x01")
+            .map_err(|err| format!("Could not send protocol header: {}", err))?;
+
+        expect_method(&mut stream, CONNECTION_START)?;
+
+        let mut start_ok = Vec::new();
+        start_ok.extend_from_slice(&0u32.to_be_bytes()); // empty client-properties
+        short_string(&mut start_ok, "PLAIN");
+        long_string(
+            &mut start_ok,
+            format!("\0{}\0{}", self.username, self.password).as_bytes(),
+        );
+        short_string(&mut start_ok, "en_US");
+        send_method(&mut stream, CONNECTION_START_OK, &start_ok)?;
+
+        let tune = expect_method(&mut stream, CONNECTION_TUNE)?;
+        if tune.len() < 8 {
+            return Err("Malformed Connection.Tune".to_string());
+        }
+        // Accept the server's channel-max and frame-max, without heartbeats.
+        let mut tune_ok = tune[..6].to_vec();
+        tune_ok.extend_from_slice(&0u16.to_be_bytes());
+        send_method(&mut stream, CONNECTION_TUNE_OK, &tune_ok)?;
+
+        let mut open = Vec::new();
+        short_string(&mut open, &self.vhost);
+        short_string(&mut open, "");
+        open.push(0);
+        send_method(&mut stream, CONNECTION_OPEN, &open)?;
+        expect_method(&mut stream, CONNECTION_OPEN_OK)?;
+
+        let mut close = Vec::new();
+        close.extend_from_slice(&200u16.to_be_bytes());
+        short_string(&mut close, "");
+        close.extend_from_slice(&[0, 0, 0, 0]);
+        // The connection is healthy at this point; a failed close does not
+        // change that.
+        let _ = send_method(&mut stream, CONNECTION_CLOSE, &close);
+
+        Ok(format!(
+            "{}:{} opened vhost {}",
+            self.host, self.port, self.vhost
+        ))
+    }
+}
+
+fn short_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.push(value.len().min(255) as u8);
+    buffer.extend_from_slice(&value.as_bytes()[..value.len().min(255)]);
+}
+
+fn long_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+fn send_method(stream: &mut TcpStream, method: (u16, u16), arguments: &[u8]) -> Result<(), String> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&method.0.to_be_bytes());
+    payload.extend_from_slice(&method.1.to_be_bytes());
+    payload.extend_from_slice(arguments);
+
+    let mut frame = vec![FRAME_METHOD, 0, 0];
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    frame.push(FRAME_END);
+
+    stream
+        .write_all(&frame)
+        .map_err(|err| format!("Could not send frame: {}", err))
+}
+
+/// Reads the next method frame and returns its arguments if it is `method`.
+/// A Connection.Close from the server is turned into its reply text.
+fn expect_method(stream: &mut TcpStream, method: (u16, u16)) -> Result<Vec<u8>, String> {
+    let mut header = [0; 7];
+    stream
+        .read_exact(&mut header)
+        .map_err(|err| format!("Could not read frame: {}", err))?;
+    if &header[..4] == b"AMQP" {
+        return Err("Server does not speak AMQP 0-9-1".to_string());
+    }
+
+    let size = u32::from_be_bytes([header[3], header[4], header[5], header[6]]) as usize;
+    if size > MAX_FRAME_SIZE {
+        return Err(format!(
+            "Frame of {} bytes exceeds the limit of {}",
+            size, MAX_FRAME_SIZE
+        ));
+    }
+    let mut payload = vec![0; size + 1];
+    stream
+        .read_exact(&mut payload)
+        .map_err(|err| format!("Could not read frame: {}", err))?;
+    if header[0] != FRAME_METHOD || payload.pop() != Some(FRAME_END) || payload.len() < 4 {
+        return Err("Unexpected frame".to_string());
+    }
+
+    let received = (
+        u16::from_be_bytes([payload[0], payload[1]]),
+        u16::from_be_bytes([payload[2], payload[3]]),
+    );
+    let arguments = payload.split_off(4);
+
+    if received == CONNECTION_CLOSE && received != method {
+        let reply_text = arguments
+            .get(2)
+            .and_then(|len| arguments.get(3..3 + *len as usize))
+            .map(|text| String::from_utf8_lossy(text).to_string())
+            .unwrap_or_default();
+        return Err(format!("Server closed the connection: {}", reply_text));
+    }
+    if received != method {
+        return Err(format!(
+            "Expected method {}.{}, got {}.{}",
+            method.0, method.1, received.0, received.1
+        ));
+    }
+    Ok(arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use crate::protocol::ResultStatus;
+
+    use super::*;
+
+    fn method_frame(method: (u16, u16), arguments: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&method.0.to_be_bytes());
+        payload.extend_from_slice(&method.1.to_be_bytes());
+        payload.extend_from_slice(arguments);
+
+        let mut frame = vec![FRAME_METHOD, 0, 0];
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+        frame.push(FRAME_END);
+        frame
+    }
+
+    /// Reads one frame and returns its class and method ids.
+    fn read_method(stream: &mut TcpStream) -> (u16, u16) {
+        let mut header = [0; 7];
+        stream.read_exact(&mut header).unwrap();
+        let size = u32::from_be_bytes([header[3], header[4], header[5], header[6]]) as usize;
+        let mut payload = vec![0; size + 1];
+        stream.read_exact(&mut payload).unwrap();
+        (
+            u16::from_be_bytes([payload[0], payload[1]]),
+            u16::from_be_bytes([payload[2], payload[3]]),
+        )
+    }
+
+    /// Accepts one connection, checks the protocol header and hands the
+    /// stream to `broker`.
+    fn stub(
+        broker: impl FnOnce(&mut TcpStream) -> Vec<(u16, u16)> + Send + 'static,
+    ) -> (u16, std::thread::JoinHandle<Vec<(u16, u16)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut protocol_header = [0; 8];
+            stream.read_exact(&mut protocol_header).unwrap();
+            assert_eq!(&protocol_header, b"AMQP\x00\x00\x09\x01");
+            broker(&mut stream)
+        });
+        (port, server)
+    }
+
+    fn check(port: u16) -> AmqpCheck {
+        AmqpCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "guest".to_string(),
+            password: "guest".to_string(),
+            vhost: "/".to_string(),
+            timeout_secs: 2,
+        }
+    }
+
+    #[test]
+    fn completes_handshake_and_closes() {
+        let (port, server) = stub(|stream| {
+            let mut received = Vec::new();
+            stream
+                .write_all(&method_frame(CONNECTION_START, &[0, 9]))
+                .unwrap();
+            received.push(read_method(stream));
+
+            let mut tune = Vec::new();
+            tune.extend_from_slice(&2047u16.to_be_bytes());
+            tune.extend_from_slice(&131072u32.to_be_bytes());
+            tune.extend_from_slice(&60u16.to_be_bytes());
+            stream
+                .write_all(&method_frame(CONNECTION_TUNE, &tune))
+                .unwrap();
+            received.push(read_method(stream));
+            received.push(read_method(stream));
+
+            stream
+                .write_all(&method_frame(CONNECTION_OPEN_OK, &[0]))
+                .unwrap();
+            received.push(read_method(stream));
+            received
+        });
+
+        let output = check(port).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                CONNECTION_START_OK,
+                CONNECTION_TUNE_OK,
+                CONNECTION_OPEN,
+                CONNECTION_CLOSE
+            ]
+        );
+    }
+
+    #[test]
+    fn server_close_reports_reply_text() {
+        let (port, server) = stub(|stream| {
+            stream
+                .write_all(&method_frame(CONNECTION_START, &[0, 9]))
+                .unwrap();
+            read_method(stream);
+
+            let mut close = Vec::new();
+            close.extend_from_slice(&403u16.to_be_bytes());
+            short_string(&mut close, "ACCESS_REFUSED");
+            close.extend_from_slice(&[0, 0, 0, 0]);
+            stream
+                .write_all(&method_frame(CONNECTION_CLOSE, &close))
+                .unwrap();
+            Vec::new()
+        });
+
+        let output = check(port).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("ACCESS_REFUSED"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn oversized_frame_is_rejected() {
+        let (port, server) = stub(|stream| {
+            let mut header = vec![FRAME_METHOD, 0, 0];
+            header.extend_from_slice(&u32::MAX.to_be_bytes());
+            stream.write_all(&header).unwrap();
+            Vec::new()
+        });
+
+        let output = check(port).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("exceeds the limit"));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,402 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use hickory_proto::{
+    op::{Message, MessageType, OpCode, Query, ResponseCode},
+    rr::{Name, RecordType},
+};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::protocol::{ModuleOutput, ResultStatus};
+
+use super::output;
+
+/// Resolves a name against a resolver over UDP and checks the answers.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DnsCheck {
+    pub name: String,
+    #[serde(default)]
+    pub record_type: DnsRecordType,
+    /// `ip:port` of the resolver, the first nameserver of
+    /// `/etc/resolv.conf` when unset.
+    pub resolver: Option<String>,
+    /// Values that all have to be among the answers.
+    #[serde(default)]
+    pub expected: Vec<String>,
+    /// Regex at least one answer has to match.
+    pub expected_regex: Option<String>,
+    /// `expected_regex` compiled by `load`.
+    #[serde(skip)]
+    expected_pattern: Option<Regex>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    #[default]
+    A,
+    Aaaa,
+    Cname,
+    Txt,
+    Mx,
+}
+
+impl From<DnsRecordType> for RecordType {
+    fn from(record_type: DnsRecordType) -> Self {
+        match record_type {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::Aaaa => RecordType::AAAA,
+            DnsRecordType::Cname => RecordType::CNAME,
+            DnsRecordType::Txt => RecordType::TXT,
+            DnsRecordType::Mx => RecordType::MX,
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+impl DnsCheck {
+    /// Validates the settings and compiles the regex.
+    pub fn load(&mut self) -> Result<(), String> {
+        Name::from_str(&self.name).map_err(|err| format!("Invalid name: {}", err))?;
+        if let Some(resolver) = &self.resolver {
+            SocketAddr::from_str(resolver)
+                .map_err(|err| format!("Invalid resolver address: {}", err))?;
+        }
+        if let Some(expected_regex) = &self.expected_regex {
+            let regex = Regex::new(expected_regex)
+                .map_err(|err| format!("Invalid expected_regex: {}", err))?;
+            self.expected_pattern = Some(regex);
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let started = std::time::Instant::now();
+
+        let answers = match self.resolve() {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Could not resolve {}: {}", self.name, err),
+                )
+            }
+        };
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (status, message) = match self.assert_answers(&answers) {
+            Ok(_) => (
+                ResultStatus::Ok,
+                format!("{} resolved to {}", self.name, answers.join(", ")),
+            ),
+            Err(err) => (ResultStatus::Critical, err),
+        };
+
+        let mut module_output = output(status, message);
+        module_output.latency_ms = Some(latency_ms);
+        module_output
+            .metrics
+            .insert("answers".to_string(), answers.len() as f64);
+        module_output
+    }
+
+    fn assert_answers(&self, answers: &[String]) -> Result<(), String> {
+        if answers.is_empty() {
+            return Err(format!(
+                "{} has no {:?} records",
+                self.name, self.record_type
+            ));
+        }
+
+        for expected in self.expected.iter() {
+            let expected = expected.trim_end_matches('.');
+            if !answers.iter().any(|answer| answer == expected) {
+                return Err(format!(
+                    "{} resolved to {}, missing {}",
+                    self.name,
+                    answers.join(", "),
+                    expected
+                ));
+            }
+        }
+
+        if let Some(regex) = &self.expected_pattern {
+            if !answers.iter().any(|answer| regex.is_match(answer)) {
+                return Err(format!("No answer of {} matches `{}`", self.name, regex));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends one query and returns the answers of the requested type,
+    /// without trailing dots.
+    fn resolve(&self) -> Result<Vec<String>, String> {
+        let resolver = match &self.resolver {
+            Some(val) => val.clone(),
+            None => system_resolver()?,
+        };
+        let resolver = SocketAddr::from_str(&resolver)
+            .map_err(|err| format!("Invalid resolver address: {}", err))?;
+
+        let name = Name::from_str(&self.name).map_err(|err| err.to_string())?;
+        let record_type = RecordType::from(self.record_type);
+        let id = (super::unix_seconds() as u16) ^ (std::process::id() as u16);
+
+        let mut query = Message::new();
+        query
+            .set_id(id)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(Query::query(name, record_type));
+        let query = query.to_vec().map_err(|err| err.to_string())?;
+
+        let bind_address = if resolver.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind_address).map_err(|err| err.to_string())?;
+        let deadline = Instant::now() + Duration::from_secs(self.timeout_secs);
+        socket
+            .send_to(&query, resolver)
+            .map_err(|err| format!("Could not query {}: {}", resolver, err))?;
+
+        // Stray or malformed packets are skipped, but do not extend the
+        // time the resolver is given.
+        let mut buffer = [0; 4096];
+        let response = loop {
+            let time_left = deadline.saturating_duration_since(Instant::now());
+            if time_left.is_zero() {
+                return Err(format!("No answer from {}", resolver));
+            }
+            socket
+                .set_read_timeout(Some(time_left))
+                .map_err(|err| err.to_string())?;
+
+            let (read, from) = socket
+                .recv_from(&mut buffer)
+                .map_err(|err| format!("No answer from {}: {}", resolver, err))?;
+            if from != resolver {
+                continue;
+            }
+            let Ok(response) = Message::from_vec(&buffer[..read]) else {
+                continue;
+            };
+            if response.id() == id {
+                break response;
+            }
+        };
+
+        if response.response_code() != ResponseCode::NoError {
+            return Err(format!("Resolver answered {}", response.response_code()));
+        }
+
+        Ok(response
+            .answers()
+            .iter()
+            .filter(|record| record.record_type() == record_type)
+            .filter_map(|record| record.data())
+            .map(|data| data.to_string().trim_end_matches('.').to_string())
+            .collect())
+    }
+}
+
+fn system_resolver() -> Result<String, String> {
+    let resolv_conf = std::fs::read_to_string("/etc/resolv.conf")
+        .map_err(|err| format!("Could not read /etc/resolv.conf: {}", err))?;
+
+    for line in resolv_conf.lines() {
+        let mut parts = line.split_whitespace();
+        if parts.next() != Some("nameserver") {
+            continue;
+        }
+        if let Some(address) = parts.next() {
+            if address.contains(':') {
+                return Ok(format!("[{}]:53", address));
+            }
+            return Ok(format!("{}:53", address));
+        }
+    }
+
+    Err("No nameserver in /etc/resolv.conf".to_string())
+}
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use hickory_proto::rr::{
+        rdata::{A, CNAME},
+        RData, Record,
+    };
+
+    use super::*;
+
+    /// Answers one query with `records` and `response_code`.
+    fn stub(
+        response_code: ResponseCode,
+        records: impl FnOnce(&Name) -> Vec<Record> + Send + 'static,
+    ) -> (String, std::thread::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (read, from) = socket.recv_from(&mut buffer).unwrap();
+            let query = Message::from_vec(&buffer[..read]).unwrap();
+            let name = query.queries()[0].name().clone();
+
+            let mut response = Message::new();
+            response
+                .set_id(query.id())
+                .set_message_type(MessageType::Response)
+                .set_op_code(OpCode::Query)
+                .set_response_code(response_code)
+                .add_queries(query.queries().to_vec())
+                .add_answers(records(&name));
+            socket.send_to(&response.to_vec().unwrap(), from).unwrap();
+        });
+        (address, server)
+    }
+
+    fn a_records(name: &Name) -> Vec<Record> {
+        vec![
+            Record::from_rdata(
+                name.clone(),
+                60,
+                RData::CNAME(CNAME(Name::from_str("alias.example.com.").unwrap())),
+            ),
+            Record::from_rdata(name.clone(), 60, RData::A(A(Ipv4Addr::new(10, 0, 0, 1)))),
+            Record::from_rdata(name.clone(), 60, RData::A(A(Ipv4Addr::new(10, 0, 0, 2)))),
+        ]
+    }
+
+    fn check(resolver: String, expected: &[&str], expected_regex: Option<&str>) -> DnsCheck {
+        let mut check = DnsCheck {
+            name: "example.com".to_string(),
+            record_type: DnsRecordType::A,
+            resolver: Some(resolver),
+            expected: expected.iter().map(|value| value.to_string()).collect(),
+            expected_regex: expected_regex.map(str::to_string),
+            expected_pattern: None,
+            timeout_secs: 2,
+        };
+        check.load().unwrap();
+        check
+    }
+
+    #[test]
+    fn matching_answers_are_ok() {
+        let (resolver, server) = stub(ResponseCode::NoError, a_records);
+
+        let output = check(resolver, &["10.0.0.2"], Some(r"^10\.0\.0\.1$")).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        // The CNAME is not an answer of the requested type.
+        assert_eq!(output.metrics.get("answers"), Some(&2.0));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn missing_expected_value_is_critical() {
+        let (resolver, server) = stub(ResponseCode::NoError, a_records);
+
+        let output = check(resolver, &["10.0.0.3"], None).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("missing 10.0.0.3"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn unmatched_regex_is_critical() {
+        let (resolver, server) = stub(ResponseCode::NoError, a_records);
+
+        let output = check(resolver, &[], Some(r"^192\.168\.")).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("No answer"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn error_response_is_critical() {
+        let (resolver, server) = stub(ResponseCode::NXDomain, |_| Vec::new());
+
+        let output = check(resolver, &[], None).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("Resolver answered"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn malformed_packets_are_skipped() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = socket.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (read, from) = socket.recv_from(&mut buffer).unwrap();
+            socket.send_to(b"not dns", from).unwrap();
+
+            let query = Message::from_vec(&buffer[..read]).unwrap();
+            let mut response = Message::new();
+            response
+                .set_id(query.id())
+                .set_message_type(MessageType::Response)
+                .add_queries(query.queries().to_vec())
+                .add_answers(a_records(query.queries()[0].name()));
+            socket.send_to(&response.to_vec().unwrap(), from).unwrap();
+        });
+
+        let output = check(resolver, &["10.0.0.1"], None).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn stray_packets_do_not_extend_the_timeout() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = socket.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (_, from) = socket.recv_from(&mut buffer).unwrap();
+            for _ in 0..50 {
+                if socket.send_to(b"not dns", from).is_err() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let mut check = check(resolver, &[], None);
+        check.timeout_secs = 1;
+
+        let started = Instant::now();
+        let output = check.run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn invalid_settings_fail_to_load() {
+        let mut check = check("127.0.0.1:53".to_string(), &[], None);
+        check.expected_regex = Some("(".to_string());
+        assert!(check.load().is_err());
+
+        check.expected_regex = None;
+        check.resolver = Some("localhost".to_string());
+        assert!(check.load().is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::protocol::{ModuleOutput, ResultStatus};
+
+use super::output;
+
+/// Runs a local executable following the Nagios plugin conventions: the exit
+/// code is the status and the first output line may carry performance data
+/// after a `|`, which is reported as metrics.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecCheck {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The process and everything it started are killed and the check
+    /// reported as unknown after this.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+impl ExecCheck {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.command.is_empty() {
+            return Err("command must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let started = Instant::now();
+        let (exit_code, stdout, stderr) = match self.execute() {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Crash,
+                    format!("Could not run {}: {}", self.command, err),
+                )
+            }
+        };
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (text, metrics) = parse_plugin_output(&stdout);
+        let text = if text.is_empty() {
+            stderr.trim().to_string()
+        } else {
+            text
+        };
+
+        // 0 OK, 1 WARNING, 2 CRITICAL, anything else UNKNOWN.
+        let status = match exit_code {
+            Some(0) => ResultStatus::Ok,
+            Some(1) => ResultStatus::Warning,
+            Some(2) => ResultStatus::Critical,
+            _ => ResultStatus::Unknown,
+        };
+
+        let mut module_output = output(status, text);
+        module_output.latency_ms = Some(latency_ms);
+        module_output.metrics = metrics;
+        if let Some(exit_code) = exit_code {
+            module_output
+                .metrics
+                .insert("exit_code".to_string(), exit_code as f64);
+        }
+        module_output
+    }
+
+    /// Runs the command to completion or until the timeout, returning the
+    /// exit code (None when killed) and the captured output.
+    fn execute(&self) -> Result<(Option<i32>, String, String), String> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Its own process group, so a timeout also kills the processes
+            // the plugin started.
+            .process_group(0)
+            .spawn()
+            .map_err(|err| err.to_string())?;
+
+        // Read on separate threads so a chatty plugin cannot block on a full
+        // pipe while we wait for it.
+        let stdout = child.stdout.take().map(read_in_background);
+        let stderr = child.stderr.take().map(read_in_background);
+
+        let deadline = Instant::now() + Duration::from_secs(self.timeout_secs);
+        let exit_code = loop {
+            match child.try_wait().map_err(|err| err.to_string())? {
+                Some(status) => break status.code(),
+                None if Instant::now() >= deadline => {
+                    unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break None;
+                }
+                None => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        // A process that left the group can still hold the pipes open, so the
+        // readers only get a short grace period after the deadline.
+        let grace = deadline.max(Instant::now()) + READ_GRACE;
+        let stdout = stdout
+            .and_then(|reader| join_until(reader, grace))
+            .unwrap_or_default();
+        let mut stderr = stderr
+            .and_then(|reader| join_until(reader, grace))
+            .unwrap_or_default();
+        if exit_code.is_none() {
+            if !stderr.is_empty() && !stderr.ends_with('\n') {
+                stderr.push('\n');
+            }
+            stderr.push_str(&format!("Killed after {} seconds", self.timeout_secs));
+        }
+
+        Ok((exit_code, stdout, stderr))
+    }
+}
+
+/// How long the output readers may run on once the deadline has passed.
+const READ_GRACE: Duration = Duration::from_secs(1);
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        String::from_utf8_lossy(&buffer).to_string()
+    })
+}
+
+/// The reader's output, or None when it is still blocked at `until`. The
+/// thread is then left to finish on its own once the pipe closes.
+fn join_until(reader: JoinHandle<String>, until: Instant) -> Option<String> {
+    while !reader.is_finished() {
+        if Instant::now() >= until {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    reader.join().ok()
+}
+
+/// Splits plugin output into its text and the performance data of the form
+/// `'label'=value[unit];warn;crit;min;max`. Perfdata follows a `|` on the
+/// first line; a `|` on a later line starts perfdata that runs to the end.
+fn parse_plugin_output(stdout: &str) -> (String, HashMap<String, f64>) {
+    let mut text = Vec::new();
+    let mut metrics = HashMap::new();
+    let mut in_perfdata = false;
+
+    for (index, line) in stdout.lines().enumerate() {
+        if in_perfdata {
+            parse_perfdata(line, &mut metrics);
+            continue;
+        }
+
+        let line_text = match line.split_once('|') {
+            Some((line_text, perfdata)) => {
+                parse_perfdata(perfdata, &mut metrics);
+                in_perfdata = index > 0;
+                line_text
+            }
+            None => line,
+        };
+        if !line_text.trim().is_empty() {
+            text.push(line_text.trim().to_string());
+        }
+    }
+
+    (text.join("\n"), metrics)
+}
+
+fn parse_perfdata(perfdata: &str, metrics: &mut HashMap<String, f64>) {
+    for entry in perfdata_entries(perfdata) {
+        let Some((label, value)) = entry.split_once('=') else {
+            continue;
+        };
+        let value = value.split(';').next().unwrap_or_default();
+        let number: String = value
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))
+            .collect();
+        if let Ok(number) = number.parse::<f64>() {
+            let label = label.strip_prefix('\'').unwrap_or(label);
+            let label = label.strip_suffix('\'').unwrap_or(label);
+            metrics.insert(label.replace("''", "'"), number);
+        }
+    }
+}
+
+/// Splits perfdata on whitespace, except inside single-quoted labels such as
+/// `'/ used'=42%`.
+fn perfdata_entries(perfdata: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+
+    for (index, c) in perfdata.char_indices() {
+        if c.is_whitespace() && !quoted {
+            if let Some(entry_start) = start.take() {
+                entries.push(&perfdata[entry_start..index]);
+            }
+            continue;
+        }
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(entry_start) = start {
+        entries.push(&perfdata[entry_start..]);
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str, timeout_secs: u64) -> ExecCheck {
+        ExecCheck {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::new(),
+            timeout_secs,
+        }
+    }
+
+    #[test]
+    fn splits_text_and_first_line_perfdata() {
+        let (text, metrics) =
+            parse_plugin_output("DISK OK - 42% used | '/ used'=42%;80;90;0;100 inodes=1.5e3\n");
+
+        assert_eq!(text, "DISK OK - 42% used");
+        assert_eq!(metrics.get("/ used"), Some(&42.0));
+        assert_eq!(metrics.get("inodes"), Some(&1500.0));
+    }
+
+    #[test]
+    fn perfdata_after_later_line_runs_to_the_end() {
+        let (text, metrics) =
+            parse_plugin_output("HTTP OK\nfetched in 0.2s | time=0.2s\nsize=512B\nnot perfdata\n");
+
+        assert_eq!(text, "HTTP OK\nfetched in 0.2s");
+        assert_eq!(metrics.get("time"), Some(&0.2));
+        assert_eq!(metrics.get("size"), Some(&512.0));
+        assert_eq!(metrics.len(), 2);
+    }
+
+    #[test]
+    fn unescapes_quotes_in_labels() {
+        let (_, metrics) = parse_plugin_output("OK | 'it''s up'=1");
+
+        assert_eq!(metrics.get("it's up"), Some(&1.0));
+    }
+
+    #[test]
+    fn skips_malformed_perfdata() {
+        let (text, metrics) = parse_plugin_output("OK | broken novalue= label=U;1;2");
+
+        assert_eq!(text, "OK");
+        assert!(metrics.is_empty());
+    }
+
+    #[test]
+    fn exit_code_decides_status() {
+        let output = shell("echo 'LOAD WARNING | load1=3.5'; exit 1", 5).run();
+        assert_eq!(output.status, Some(ResultStatus::Warning));
+        assert_eq!(output.message.as_deref(), Some("LOAD WARNING"));
+        assert_eq!(output.metrics.get("load1"), Some(&3.5));
+        assert_eq!(output.metrics.get("exit_code"), Some(&1.0));
+
+        assert_eq!(shell("exit 0", 5).run().status, Some(ResultStatus::Ok));
+        assert_eq!(
+            shell("exit 2", 5).run().status,
+            Some(ResultStatus::Critical)
+        );
+        assert_eq!(shell("exit 3", 5).run().status, Some(ResultStatus::Unknown));
+    }
+
+    #[test]
+    fn falls_back_to_stderr_without_stdout() {
+        let output = shell("echo 'no such host' >&2; exit 2", 5).run();
+
+        assert_eq!(output.message.as_deref(), Some("no such host"));
+    }
+
+    #[test]
+    fn timeout_kills_processes_started_by_the_plugin() {
+        let started = Instant::now();
+        let output = shell("sleep 30 & echo partial; printf oops >&2; sleep 30", 1).run();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(output.status, Some(ResultStatus::Unknown));
+        assert_eq!(output.message.as_deref(), Some("partial"));
+
+        let (_, _, stderr) = shell("printf oops >&2; sleep 30", 1).execute().unwrap();
+        assert_eq!(stderr, "oops\nKilled after 1 seconds");
+    }
+
+    #[test]
+    fn missing_command_is_a_crash() {
+        let output = ExecCheck {
+            command: "/nonexistent/check_plugin".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            timeout_secs: 5,
+        }
+        .run();
+
+        assert_eq!(output.status, Some(ResultStatus::Crash));
+    }
+}
@@ -0,0 +1,227 @@
+use std::{fs::File, io::Read, time::SystemTime};
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::protocol::{ModuleOutput, ResultStatus};
+
+use super::output;
+
+/// Checks that a file exists, was modified recently and, optionally, its
+/// size and content. Meant for batch jobs that touch a file on success.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileCheck {
+    pub path: String,
+    /// Down when the file was last modified longer ago than this.
+    pub max_age_secs: Option<u64>,
+    pub min_size_bytes: Option<u64>,
+    /// Regex the file content has to match.
+    pub content_regex: Option<String>,
+    /// `content_regex` compiled by `load`.
+    #[serde(skip)]
+    content_pattern: Option<Regex>,
+    /// Only this many bytes from the start of the file are matched against
+    /// `content_regex`.
+    #[serde(default = "default_max_read_bytes")]
+    pub max_read_bytes: u64,
+}
+
+fn default_max_read_bytes() -> u64 {
+    1024 * 1024
+}
+
+impl FileCheck {
+    /// Validates the settings and compiles the regex.
+    pub fn load(&mut self) -> Result<(), String> {
+        if let Some(content_regex) = &self.content_regex {
+            let regex = Regex::new(content_regex)
+                .map_err(|err| format!("Invalid content_regex: {}", err))?;
+            self.content_pattern = Some(regex);
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let metadata = match std::fs::metadata(&self.path) {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Could not stat {}: {}", self.path, err),
+                )
+            }
+        };
+
+        let age_secs = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age.as_secs())
+            .unwrap_or_default();
+
+        let (status, message) = match self.assert_file(age_secs, metadata.len()) {
+            Ok(_) => (
+                ResultStatus::Ok,
+                format!("{} was modified {} seconds ago", self.path, age_secs),
+            ),
+            Err(err) => (ResultStatus::Critical, err),
+        };
+
+        let mut module_output = output(status, message);
+        module_output
+            .metrics
+            .insert("age_seconds".to_string(), age_secs as f64);
+        module_output
+            .metrics
+            .insert("size_bytes".to_string(), metadata.len() as f64);
+        module_output
+    }
+
+    fn assert_file(&self, age_secs: u64, size: u64) -> Result<(), String> {
+        if let Some(max_age_secs) = self.max_age_secs {
+            if age_secs > max_age_secs {
+                return Err(format!(
+                    "{} was modified {} seconds ago, more than {}",
+                    self.path, age_secs, max_age_secs
+                ));
+            }
+        }
+
+        if let Some(min_size_bytes) = self.min_size_bytes {
+            if size < min_size_bytes {
+                return Err(format!(
+                    "{} has {} bytes, less than {}",
+                    self.path, size, min_size_bytes
+                ));
+            }
+        }
+
+        if let Some(regex) = &self.content_pattern {
+            let mut content = Vec::new();
+            File::open(&self.path)
+                .and_then(|file| file.take(self.max_read_bytes).read_to_end(&mut content))
+                .map_err(|err| format!("Could not read {}: {}", self.path, err))?;
+            if !regex.is_match(&String::from_utf8_lossy(&content)) {
+                return Err(format!("{} does not match `{}`", self.path, regex));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// A file in the temp dir named after the test, removed on drop.
+    struct TempFile(std::path::PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("health-check-{}-{}", std::process::id(), name));
+            std::fs::write(&path, content).unwrap();
+            TempFile(path)
+        }
+
+        fn check(&self, settings: &str) -> FileCheck {
+            let mut check: FileCheck =
+                toml::from_str(&format!("path = {:?}\n{}", self.0, settings)).unwrap();
+            check.load().unwrap();
+            check
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn missing_file_is_critical() {
+        let file = TempFile::new("missing", b"");
+        std::fs::remove_file(&file.0).unwrap();
+
+        let output = file.check("").run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().starts_with("Could not stat"));
+    }
+
+    #[test]
+    fn old_file_is_critical() {
+        let file = TempFile::new("old", b"done");
+        let two_hours_ago = SystemTime::now() - Duration::from_secs(2 * 3600);
+        File::options()
+            .write(true)
+            .open(&file.0)
+            .unwrap()
+            .set_modified(two_hours_ago)
+            .unwrap();
+
+        let output = file.check("max_age_secs = 3600").run();
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.metrics["age_seconds"] >= 7200.0);
+
+        let output = file.check("max_age_secs = 10800").run();
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+    }
+
+    #[test]
+    fn small_file_is_critical() {
+        let file = TempFile::new("small", b"1234");
+
+        assert_eq!(
+            file.check("min_size_bytes = 5").run().status,
+            Some(ResultStatus::Critical)
+        );
+        let output = file.check("min_size_bytes = 4").run();
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(output.metrics.get("size_bytes"), Some(&4.0));
+    }
+
+    #[test]
+    fn content_has_to_match_regex() {
+        let file = TempFile::new("content", b"backup finished: OK\n");
+
+        assert_eq!(
+            file.check("content_regex = \"finished: OK\"").run().status,
+            Some(ResultStatus::Ok)
+        );
+        let output = file.check("content_regex = \"finished: FAILED\"").run();
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output
+            .message
+            .unwrap()
+            .ends_with("does not match `finished: FAILED`"));
+    }
+
+    #[test]
+    fn content_is_only_read_up_to_max_read_bytes() {
+        let file = TempFile::new("large", b"header\nfooter\n");
+
+        let settings = "content_regex = \"footer\"\nmax_read_bytes = 7";
+        assert_eq!(
+            file.check(settings).run().status,
+            Some(ResultStatus::Critical)
+        );
+        let settings = "content_regex = \"header\"\nmax_read_bytes = 7";
+        assert_eq!(file.check(settings).run().status, Some(ResultStatus::Ok));
+    }
+
+    #[test]
+    fn invalid_regex_fails_to_load() {
+        let mut check: FileCheck =
+            toml::from_str("path = \"/tmp\"\ncontent_regex = \"(\"").unwrap();
+
+        assert!(check
+            .load()
+            .unwrap_err()
+            .starts_with("Invalid content_regex"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::{collections::HashMap, time::Duration};
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::protocol::{ModuleOutput, ResultStatus};
+
+use super::output;
+
+/// Requests a URL and checks the answer.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpCheck {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default = "default_expected_status")]
+    pub expected_status: Vec<u16>,
+    /// Regex the response body has to match.
+    pub body_regex: Option<String>,
+    /// `body_regex` compiled by `load`.
+    #[serde(skip)]
+    body_pattern: Option<Regex>,
+    /// Dotted path into a JSON response body, e.g. `checks.0.state`.
+    pub json_path: Option<String>,
+    /// Value expected at `json_path`; without it the path only has to exist.
+    pub json_value: Option<serde_json::Value>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub follow_redirects: bool,
+}
+
+/// Turns a dotted path like `checks.0.state` into the JSON pointer
+/// `/checks/0/state`.
+fn json_pointer(json_path: &str) -> String {
+    format!("/{}", json_path.replace('.', "/"))
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_expected_status() -> Vec<u16> {
+    vec![200]
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl HttpCheck {
+    /// Validates the settings and compiles the regex.
+    pub fn load(&mut self) -> Result<(), String> {
+        if let Some(body_regex) = &self.body_regex {
+            let regex =
+                Regex::new(body_regex).map_err(|err| format!("Invalid body_regex: {}", err))?;
+            self.body_pattern = Some(regex);
+        }
+        if self.json_value.is_some() && self.json_path.is_none() {
+            return Err("json_value requires json_path".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .redirects(if self.follow_redirects { 5 } else { 0 })
+            .build();
+
+        let mut request = agent.request(&self.method, &self.url);
+        for (key, value) in self.headers.iter() {
+            request = request.set(key, value);
+        }
+
+        let started = std::time::Instant::now();
+        let result = if self.body.is_empty() {
+            request.call()
+        } else {
+            request.send_string(&self.body)
+        };
+        let response = match result {
+            Ok(val) => val,
+            Err(ureq::Error::Status(_, val)) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Request to {} failed: {}", self.url, err),
+                )
+            }
+        };
+
+        let status = response.status();
+        let body = match response.into_string() {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Could not read response body: {}", err),
+                )
+            }
+        };
+        let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (status_result, message) = match self.assert_response(status, &body) {
+            Ok(_) => (
+                ResultStatus::Ok,
+                format!("{} answered {}", self.url, status),
+            ),
+            Err(err) => (ResultStatus::Critical, err),
+        };
+
+        let mut module_output = output(status_result, message);
+        module_output.latency_ms = Some(latency_ms);
+        module_output
+            .metrics
+            .insert("http_status".to_string(), status as f64);
+        module_output
+    }
+
+    fn assert_response(&self, status: u16, body: &str) -> Result<(), String> {
+        if !self.expected_status.contains(&status) {
+            return Err(format!(
+                "{} answered {}, expected one of {:?}",
+                self.url, status, self.expected_status
+            ));
+        }
+
+        if let Some(regex) = &self.body_pattern {
+            if !regex.is_match(body) {
+                return Err(format!("Body does not match `{}`", regex));
+            }
+        }
+
+        if let Some(json_path) = &self.json_path {
+            let document = serde_json::from_str::<serde_json::Value>(body)
+                .map_err(|err| format!("Body is not JSON: {}", err))?;
+            let Some(value) = document.pointer(&json_pointer(json_path)) else {
+                return Err(format!("Body has no value at `{}`", json_path));
+            };
+            if let Some(expected) = &self.json_value {
+                if value != expected {
+                    return Err(format!(
+                        "Value at `{}` is {}, expected {}",
+                        json_path, value, expected
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    fn check(settings: &str) -> HttpCheck {
+        let mut check: HttpCheck = toml::from_str(settings).unwrap();
+        check.load().unwrap();
+        check
+    }
+
+    /// Answers one connection per response on 127.0.0.1, in order.
+    fn serve(responses: Vec<String>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0; 4096];
+                let _ = stream.read(&mut buffer).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        port
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nConnection: close\r\n{}Content-Length: {}\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn status_outside_expected_status_is_critical() {
+        let port = serve(vec![
+            response("503 Service Unavailable", "", ""),
+            response("503 Service Unavailable", "", ""),
+        ]);
+        let url = format!("url = \"http://127.0.0.1:{}/\"\n", port);
+
+        let output = check(&url).run();
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert_eq!(output.metrics.get("http_status"), Some(&503.0));
+
+        let output = check(&format!("{}expected_status = [200, 503]", url)).run();
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert!(output.latency_ms.is_some());
+    }
+
+    #[test]
+    fn redirects_are_only_followed_when_enabled() {
+        let redirect = || response("302 Found", "Location: /moved\r\n", "");
+        let port = serve(vec![
+            redirect(),
+            redirect(),
+            response("200 OK", "", "moved here"),
+        ]);
+        let url = format!("url = \"http://127.0.0.1:{}/\"\n", port);
+
+        let output = check(&url).run();
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert_eq!(output.metrics.get("http_status"), Some(&302.0));
+
+        let output = check(&format!("{}follow_redirects = true", url)).run();
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(output.metrics.get("http_status"), Some(&200.0));
+    }
+
+    #[test]
+    fn body_regex_has_to_match() {
+        let check = check("url = \"http://localhost/\"\nbody_regex = \"^all (good|fine)$\"");
+
+        assert_eq!(check.assert_response(200, "all good"), Ok(()));
+        assert_eq!(
+            check.assert_response(200, "all bad"),
+            Err("Body does not match `^all (good|fine)$`".to_string())
+        );
+    }
+
+    #[test]
+    fn json_path_is_a_dotted_pointer() {
+        assert_eq!(json_pointer("checks.0.state"), "/checks/0/state");
+        assert_eq!(json_pointer("status"), "/status");
+    }
+
+    #[test]
+    fn json_value_is_compared_at_json_path() {
+        let check = check(
+            "url = \"http://localhost/\"\njson_path = \"checks.0.state\"\njson_value = \"up\"",
+        );
+
+        assert_eq!(
+            check.assert_response(200, r#"{"checks": [{"state": "up"}]}"#),
+            Ok(())
+        );
+        assert_eq!(
+            check.assert_response(200, r#"{"checks": [{"state": "down"}]}"#),
+            Err("Value at `checks.0.state` is \"down\", expected \"up\"".to_string())
+        );
+        assert_eq!(
+            check.assert_response(200, r#"{"checks": []}"#),
+            Err("Body has no value at `checks.0.state`".to_string())
+        );
+    }
+
+    #[test]
+    fn non_json_body_with_json_path_is_critical() {
+        let port = serve(vec![response("200 OK", "", "<html>fine</html>")]);
+        let check = check(&format!(
+            "url = \"http://127.0.0.1:{}/\"\njson_path = \"status\"",
+            port
+        ));
+
+        let output = check.run();
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().starts_with("Body is not JSON"));
+    }
+
+    #[test]
+    fn load_rejects_invalid_settings() {
+        let mut check: HttpCheck =
+            toml::from_str("url = \"http://localhost/\"\nbody_regex = \"(\"").unwrap();
+        assert!(check.load().unwrap_err().starts_with("Invalid body_regex"));
+
+        let mut check: HttpCheck =
+            toml::from_str("url = \"http://localhost/\"\njson_value = 1").unwrap();
+        assert_eq!(
+            check.load(),
+            Err("json_value requires json_path".to_string())
+        );
+    }
+}
@@ -0,0 +1,238 @@
+mod amqp;
+mod dns;
+mod exec;
+mod file;
+mod http;
+mod postgresql;
+mod redis;
+mod system;
+mod tcp;
+mod tls;
+
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    modules::{CheckModule, Execution},
+    protocol::{ModuleOutput, ResultStatus},
+    types::RunPayload,
+};
+
+pub use amqp::AmqpCheck;
+pub use dns::DnsCheck;
+pub use exec::ExecCheck;
+pub use file::FileCheck;
+pub use http::HttpCheck;
+pub use postgresql::PostgresCheck;
+pub use redis::RedisCheck;
+pub use system::{DiskCheck, LoadCheck, MemoryCheck, ProcessCheck};
+pub use tcp::TcpCheck;
+pub use tls::TlsCheck;
+
+/// A check the host runs itself, declared in the configuration file.
+///
+/// ```toml
+/// [checks.homepage]
+/// kind = "http"
+/// url = "https://example.com"
+/// expected_status = [200, 301]
+///
+/// [checks.mail]
+/// kind = "tcp"
+/// host = "mail.example.com"
+/// port = 25
+/// expect = "220"
+///
+/// [checks.api_certificate]
+/// kind = "tls"
+/// host = "api.example.com"
+/// min_days = 21
+///
+/// [checks.mail_exchanger]
+/// kind = "dns"
+/// name = "example.com"
+/// record_type = "MX"
+/// expected_regex = "mail\\.example\\.com$"
+///
+/// [checks.load]
+/// kind = "exec"
+/// command = "/usr/lib/nagios/plugins/check_load"
+/// args = ["-w", "5,4,3", "-c", "10,8,6"]
+///
+/// [checks.data_disk]
+/// kind = "disk"
+/// path = "/var/lib/data"
+/// min_free_percent = 15
+///
+/// [checks.nginx]
+/// kind = "process"
+/// name = "nginx"
+///
+/// [checks.nightly_backup]
+/// kind = "file"
+/// path = "/var/backups/last_success"
+/// max_age_secs = 90000
+///
+/// [checks.cache]
+/// kind = "redis"
+/// host = "127.0.0.1"
+/// password = "secret"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckConfig {
+    Http(HttpCheck),
+    Tcp(TcpCheck),
+    Tls(TlsCheck),
+    Dns(DnsCheck),
+    Exec(ExecCheck),
+    Disk(DiskCheck),
+    Memory(MemoryCheck),
+    Load(LoadCheck),
+    Process(ProcessCheck),
+    File(FileCheck),
+    Redis(RedisCheck),
+    Postgres(PostgresCheck),
+    Amqp(AmqpCheck),
+}
+
+impl CheckConfig {
+    /// Validates the settings and prepares what every run reuses, such as
+    /// compiled regexes.
+    fn load(&mut self) -> Result<(), String> {
+        match self {
+            CheckConfig::Http(check) => check.load(),
+            CheckConfig::Tcp(check) => check.validate(),
+            CheckConfig::Tls(check) => check.validate(),
+            CheckConfig::Dns(check) => check.load(),
+            CheckConfig::Exec(check) => check.validate(),
+            CheckConfig::Disk(check) => check.validate(),
+            CheckConfig::Memory(_)
+            | CheckConfig::Load(_)
+            | CheckConfig::Redis(_)
+            | CheckConfig::Postgres(_)
+            | CheckConfig::Amqp(_) => Ok(()),
+            CheckConfig::Process(check) => check.validate(),
+            CheckConfig::File(check) => check.load(),
+        }
+    }
+
+    fn run(&self) -> ModuleOutput {
+        match self {
+            CheckConfig::Http(check) => check.run(),
+            CheckConfig::Tcp(check) => check.run(),
+            CheckConfig::Tls(check) => check.run(),
+            CheckConfig::Dns(check) => check.run(),
+            CheckConfig::Exec(check) => check.run(),
+            CheckConfig::Disk(check) => check.run(),
+            CheckConfig::Memory(check) => check.run(),
+            CheckConfig::Load(check) => check.run(),
+            CheckConfig::Process(check) => check.run(),
+            CheckConfig::File(check) => check.run(),
+            CheckConfig::Redis(check) => check.run(),
+            CheckConfig::Postgres(check) => check.run(),
+            CheckConfig::Amqp(check) => check.run(),
+        }
+    }
+}
+
+/// Drives a [`CheckConfig`] like a worker module.
+pub struct BuiltinCheck {
+    name: String,
+    config: CheckConfig,
+    last_output: Option<ModuleOutput>,
+}
+
+impl BuiltinCheck {
+    pub fn new(name: String, config: CheckConfig) -> Self {
+        BuiltinCheck {
+            name,
+            config,
+            last_output: None,
+        }
+    }
+}
+
+impl CheckModule for BuiltinCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fresh_instance(&self) -> Box<dyn CheckModule> {
+        Box::new(BuiltinCheck::new(self.name.clone(), self.config.clone()))
+    }
+
+    fn load(&mut self) -> Result<(), String> {
+        self.config.load()
+    }
+
+    fn execute(&mut self, _payload: Option<&RunPayload>) -> Result<Execution, String> {
+        let output = self.config.run();
+        let stdout = output.message.clone().unwrap_or_default();
+        self.last_output = Some(output);
+
+        Ok(Execution {
+            exit_code: None,
+            stdout,
+            stderr: String::new(),
+            metrics: Default::default(),
+        })
+    }
+
+    fn parse(&self, _execution: &Execution) -> ModuleOutput {
+        self.last_output.clone().unwrap_or_default()
+    }
+}
+
+/// Output of a check that ended with the given status and message.
+fn output(status: ResultStatus, message: String) -> ModuleOutput {
+    ModuleOutput {
+        status: Some(status),
+        message: Some(message),
+        ..Default::default()
+    }
+}
+
+fn unix_seconds() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Output of a probe that either succeeds or fails, with its latency.
+fn probe_output(probe: impl FnOnce() -> Result<String, String>) -> ModuleOutput {
+    let started = Instant::now();
+    let result = probe();
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let mut module_output = match result {
+        Ok(message) => output(ResultStatus::Ok, message),
+        Err(err) => output(ResultStatus::Critical, err),
+    };
+    module_output.latency_ms = Some(latency_ms);
+    module_output
+}
+
+/// Connects with `timeout` applied to the connect and to later reads and
+/// writes.
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, String> {
+    let target = format!("{}:{}", host, port);
+    let address = target
+        .to_socket_addrs()
+        .map_err(|err| format!("Could not resolve {}: {}", target, err))?
+        .next()
+        .ok_or_else(|| format!("{} did not resolve to an address", target))?;
+
+    let stream = TcpStream::connect_timeout(&address, timeout)
+        .map_err(|err| format!("Could not connect to {}: {}", target, err))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+    Ok(stream)
+}
@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio_postgres::NoTls;
+
+use crate::protocol::ModuleOutput;
+
+/// Logs in and runs `SELECT 1`. Connects without TLS. `timeout_secs` bounds
+/// the whole probe, so a server that accepts the connection and then stalls
+/// is reported as down too.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostgresCheck {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    pub password: Option<String>,
+    pub dbname: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_port() -> u16 {
+    5432
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+impl PostgresCheck {
+    pub fn run(&self) -> ModuleOutput {
+        super::probe_output(|| self.probe())
+    }
+
+    fn probe(&self) -> Result<String, String> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| format!("Could not create runtime: {}", err))?;
+
+        runtime.block_on(async {
+            let timeout = Duration::from_secs(self.timeout_secs);
+            match tokio::time::timeout(timeout, self.select_one()).await {
+                Ok(result) => result,
+                Err(_) => Err(format!(
+                    "{}:{} did not answer within {} seconds",
+                    self.host, self.port, self.timeout_secs
+                )),
+            }
+        })
+    }
+
+    async fn select_one(&self) -> Result<String, String> {
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host(&self.host)
+            .port(self.port)
+            .user(&self.user)
+            .connect_timeout(Duration::from_secs(self.timeout_secs));
+        if let Some(password) = &self.password {
+            config.password(password);
+        }
+        if let Some(dbname) = &self.dbname {
+            config.dbname(dbname);
+        }
+
+        let (client, connection) = config
+            .connect(NoTls)
+            .await
+            .map_err(|err| format!("Could not connect: {}", describe(&err)))?;
+        // Drives the socket; it ends once the client is dropped.
+        tokio::spawn(connection);
+
+        let row = client
+            .query_one("SELECT 1", &[])
+            .await
+            .map_err(|err| format!("SELECT 1 failed: {}", describe(&err)))?;
+        let value: i32 = row.try_get(0).map_err(|err| err.to_string())?;
+        if value != 1 {
+            return Err(format!("SELECT 1 returned {}", value));
+        }
+        Ok(format!("{}:{} answered SELECT 1", self.host, self.port))
+    }
+}
+
+/// The server's message for errors it reported, which the error's own
+/// display leaves out.
+fn describe(err: &tokio_postgres::Error) -> String {
+    match err.as_db_error() {
+        Some(db_error) => db_error.message().to_string(),
+        None => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::protocol::ResultStatus;
+
+    use super::*;
+
+    fn check(port: u16) -> PostgresCheck {
+        PostgresCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            user: "health".to_string(),
+            password: None,
+            dbname: None,
+            timeout_secs: 2,
+        }
+    }
+
+    /// A backend message: tag, length including itself, body.
+    fn message(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![tag];
+        message.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+        message.extend_from_slice(body);
+        message
+    }
+
+    fn ready_for_query() -> Vec<u8> {
+        message(b'Z', b"I")
+    }
+
+    fn read_startup(stream: &mut TcpStream) {
+        let mut length = [0; 4];
+        stream.read_exact(&mut length).unwrap();
+        let mut body = vec![0; i32::from_be_bytes(length) as usize - 4];
+        stream.read_exact(&mut body).unwrap();
+    }
+
+    /// Reads frontend messages up to and including the next Sync.
+    fn read_until_sync(stream: &mut TcpStream) {
+        loop {
+            let mut header = [0; 5];
+            stream.read_exact(&mut header).unwrap();
+            let length = i32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+            let mut body = vec![0; length - 4];
+            stream.read_exact(&mut body).unwrap();
+            if header[0] == b'S' {
+                return;
+            }
+        }
+    }
+
+    /// Serves one connection, handing the accepted stream to `session`.
+    fn serve_once(session: impl FnOnce(&mut TcpStream) + Send + 'static) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_startup(&mut stream);
+            session(&mut stream);
+        });
+        port
+    }
+
+    #[test]
+    fn select_one_is_ok() {
+        let port = serve_once(|stream| {
+            // AuthenticationOk
+            stream
+                .write_all(&message(b'R', &0i32.to_be_bytes()))
+                .unwrap();
+            stream.write_all(&ready_for_query()).unwrap();
+
+            // Prepare: no parameters, one int4 column.
+            read_until_sync(stream);
+            let mut row_description = 1i16.to_be_bytes().to_vec();
+            row_description.extend_from_slice(b"?column?\0");
+            row_description.extend_from_slice(&0i32.to_be_bytes());
+            row_description.extend_from_slice(&0i16.to_be_bytes());
+            row_description.extend_from_slice(&23i32.to_be_bytes());
+            row_description.extend_from_slice(&4i16.to_be_bytes());
+            row_description.extend_from_slice(&(-1i32).to_be_bytes());
+            row_description.extend_from_slice(&0i16.to_be_bytes());
+            stream.write_all(&message(b'1', b"")).unwrap();
+            stream
+                .write_all(&message(b't', &0i16.to_be_bytes()))
+                .unwrap();
+            stream.write_all(&message(b'T', &row_description)).unwrap();
+            stream.write_all(&ready_for_query()).unwrap();
+
+            // Execute: a single row holding 1 in binary.
+            read_until_sync(stream);
+            let mut data_row = 1i16.to_be_bytes().to_vec();
+            data_row.extend_from_slice(&4i32.to_be_bytes());
+            data_row.extend_from_slice(&1i32.to_be_bytes());
+            stream.write_all(&message(b'2', b"")).unwrap();
+            stream.write_all(&message(b'D', &data_row)).unwrap();
+            stream.write_all(&message(b'C', b"SELECT 1\0")).unwrap();
+            stream.write_all(&ready_for_query()).unwrap();
+
+            let _ = stream.read(&mut [0; 64]);
+        });
+
+        let output = check(port).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(
+            output.message.as_deref(),
+            Some(format!("127.0.0.1:{} answered SELECT 1", port).as_str())
+        );
+        assert!(output.latency_ms.is_some());
+    }
+
+    #[test]
+    fn error_response_is_critical_with_server_message() {
+        let port = serve_once(|stream| {
+            let mut error = Vec::new();
+            for (field, value) in [
+                (b'S', "FATAL"),
+                (b'V', "FATAL"),
+                (b'C', "28P01"),
+                (b'M', "password authentication failed for user \"health\""),
+            ] {
+                error.push(field);
+                error.extend_from_slice(value.as_bytes());
+                error.push(0);
+            }
+            error.push(0);
+            stream.write_all(&message(b'E', &error)).unwrap();
+        });
+
+        let output = check(port).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert_eq!(
+            output.message.as_deref(),
+            Some("Could not connect: password authentication failed for user \"health\"")
+        );
+    }
+
+    #[test]
+    fn stalled_server_fails_within_timeout() {
+        // Accepts the connection but never answers the startup message.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut check = check(listener.local_addr().unwrap().port());
+        check.timeout_secs = 1;
+
+        let started = std::time::Instant::now();
+        let output = check.run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("did not answer"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+        drop(listener);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::protocol::ModuleOutput;
+
+/// Authenticates if configured and expects `PONG` to a `PING`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedisCheck {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_port() -> u16 {
+    6379
+}
+
+fn default_timeout_secs() -> u64 {
+    5
+}
+
+impl RedisCheck {
+    pub fn run(&self) -> ModuleOutput {
+        super::probe_output(|| self.probe())
+    }
+
+    fn probe(&self) -> Result<String, String> {
+        let stream = super::connect(
+            &self.host,
+            self.port,
+            Duration::from_secs(self.timeout_secs),
+        )?;
+        let mut reader = BufReader::new(stream.try_clone().map_err(|err| err.to_string())?);
+        let mut stream = stream;
+
+        if let Some(password) = &self.password {
+            let mut auth = vec!["AUTH"];
+            auth.extend(self.username.as_deref());
+            auth.push(password);
+            let reply = command(&mut stream, &mut reader, &auth)?;
+            if reply != "+OK" {
+                return Err(format!("Authentication failed: {}", reply));
+            }
+        }
+
+        let reply = command(&mut stream, &mut reader, &["PING"])?;
+        if reply != "+PONG" {
+            return Err(format!("Unexpected reply to PING: {}", reply));
+        }
+        Ok(format!("{}:{} answered PONG", self.host, self.port))
+    }
+}
+
+/// Sends a command as a RESP array and returns the first reply line.
+fn command(
+    stream: &mut TcpStream,
+    reader: &mut BufReader<TcpStream>,
+    arguments: &[&str],
+) -> Result<String, String> {
+    let mut request = format!("*{}\r\n", arguments.len());
+    for argument in arguments {
+        request.push_str(&format!("${}\r\n{}\r\n", argument.len(), argument));
+    }
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|err| format!("Could not send {}: {}", arguments[0], err))?;
+
+    let mut reply = String::new();
+    reader
+        .read_line(&mut reply)
+        .map_err(|err| format!("No reply to {}: {}", arguments[0], err))?;
+    if reply.is_empty() {
+        return Err("Connection closed".to_string());
+    }
+    Ok(reply.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use crate::protocol::ResultStatus;
+
+    use super::*;
+
+    /// Answers each RESP command with the next of `replies` and returns the
+    /// commands it received.
+    fn stub(replies: &'static [&'static str]) -> (u16, std::thread::JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = Vec::new();
+            for reply in replies {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let count: usize = line.trim_end()[1..].parse().unwrap();
+                let mut arguments = Vec::new();
+                for _ in 0..count {
+                    let (mut length, mut argument) = (String::new(), String::new());
+                    reader.read_line(&mut length).unwrap();
+                    reader.read_line(&mut argument).unwrap();
+                    arguments.push(argument.trim_end().to_string());
+                }
+                commands.push(arguments);
+                stream
+                    .write_all(format!("{}\r\n", reply).as_bytes())
+                    .unwrap();
+            }
+            commands
+        });
+        (port, server)
+    }
+
+    fn check(port: u16, password: Option<&str>) -> RedisCheck {
+        RedisCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: password.map(str::to_string),
+            timeout_secs: 2,
+        }
+    }
+
+    #[test]
+    fn authenticates_and_pings() {
+        let (port, server) = stub(&["+OK", "+PONG"]);
+
+        let output = check(port, Some("secret")).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(
+            server.join().unwrap(),
+            vec![vec!["AUTH", "secret"], vec!["PING"]]
+        );
+    }
+
+    #[test]
+    fn rejected_password_is_critical() {
+        let (port, server) = stub(&["-WRONGPASS invalid password"]);
+
+        let output = check(port, Some("wrong")).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("WRONGPASS"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn error_reply_to_ping_is_critical() {
+        let (port, server) = stub(&["-NOAUTH Authentication required."]);
+
+        let output = check(port, None).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("NOAUTH"));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,520 @@
+use std::ffi::CString;
+
+use serde::Deserialize;
+
+use crate::protocol::{ModuleOutput, ResultStatus};
+
+use super::output;
+
+/// Free space and inodes of the filesystem holding `path`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskCheck {
+    pub path: String,
+    #[serde(default = "default_min_free_percent")]
+    pub min_free_percent: f64,
+    pub min_free_inodes_percent: Option<f64>,
+}
+
+/// Memory available for new allocations, from `/proc/meminfo`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryCheck {
+    #[serde(default = "default_min_free_percent")]
+    pub min_available_percent: f64,
+}
+
+/// Load averages from `/proc/loadavg`, optionally divided by the CPU count.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoadCheck {
+    pub max_load1: Option<f64>,
+    pub max_load5: Option<f64>,
+    pub max_load15: Option<f64>,
+    #[serde(default)]
+    pub per_cpu: bool,
+}
+
+/// Processes whose name (`/proc/<pid>/comm`, or the file name of `argv[0]`
+/// for names the kernel truncates) matches, or the process whose pid is
+/// stored in `pidfile`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessCheck {
+    pub name: Option<String>,
+    pub pidfile: Option<String>,
+    #[serde(default = "default_min_count")]
+    pub min_count: usize,
+}
+
+fn default_min_free_percent() -> f64 {
+    10.0
+}
+
+fn default_min_count() -> usize {
+    1
+}
+
+impl DiskCheck {
+    pub fn validate(&self) -> Result<(), String> {
+        CString::new(self.path.as_str())
+            .map(|_| ())
+            .map_err(|err| format!("Invalid path: {}", err))
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let stats = match statvfs(&self.path) {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Could not stat {}: {}", self.path, err),
+                )
+            }
+        };
+
+        let block_size = stats.f_frsize as f64;
+        let free_bytes = stats.f_bavail as f64 * block_size;
+        let free_percent = percent(stats.f_bavail as f64, stats.f_blocks as f64);
+        let free_inodes_percent = percent(stats.f_favail as f64, stats.f_files as f64);
+
+        let mut problems = Vec::new();
+        if free_percent < self.min_free_percent {
+            problems.push(format!(
+                "{:.1}% space free, less than {}%",
+                free_percent, self.min_free_percent
+            ));
+        }
+        if let Some(min_free_inodes_percent) = self.min_free_inodes_percent {
+            if free_inodes_percent < min_free_inodes_percent {
+                problems.push(format!(
+                    "{:.1}% inodes free, less than {}%",
+                    free_inodes_percent, min_free_inodes_percent
+                ));
+            }
+        }
+
+        let mut module_output = verdict(
+            &problems,
+            format!("{:.1}% space free on {}", free_percent, self.path),
+        );
+        module_output
+            .metrics
+            .insert("free_bytes".to_string(), free_bytes);
+        module_output
+            .metrics
+            .insert("free_percent".to_string(), free_percent);
+        module_output
+            .metrics
+            .insert("free_inodes_percent".to_string(), free_inodes_percent);
+        module_output
+    }
+}
+
+impl MemoryCheck {
+    pub fn run(&self) -> ModuleOutput {
+        let meminfo = match std::fs::read_to_string("/proc/meminfo") {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Could not read /proc/meminfo: {}", err),
+                )
+            }
+        };
+        self.assess(&meminfo)
+    }
+
+    /// Rates the content of `/proc/meminfo`.
+    fn assess(&self, meminfo: &str) -> ModuleOutput {
+        let (Some(total_kib), Some(available_kib)) = (
+            meminfo_value(meminfo, "MemTotal"),
+            meminfo_value(meminfo, "MemAvailable"),
+        ) else {
+            return output(
+                ResultStatus::Critical,
+                "MemTotal or MemAvailable missing in /proc/meminfo".to_string(),
+            );
+        };
+        let available_percent = percent(available_kib, total_kib);
+
+        let mut problems = Vec::new();
+        if available_percent < self.min_available_percent {
+            problems.push(format!(
+                "{:.1}% memory available, less than {}%",
+                available_percent, self.min_available_percent
+            ));
+        }
+
+        let mut module_output = verdict(
+            &problems,
+            format!("{:.1}% memory available", available_percent),
+        );
+        module_output
+            .metrics
+            .insert("available_bytes".to_string(), available_kib * 1024.0);
+        module_output
+            .metrics
+            .insert("available_percent".to_string(), available_percent);
+        module_output
+    }
+}
+
+impl LoadCheck {
+    pub fn run(&self) -> ModuleOutput {
+        let loadavg = match std::fs::read_to_string("/proc/loadavg") {
+            Ok(val) => val,
+            Err(err) => {
+                return output(
+                    ResultStatus::Critical,
+                    format!("Could not read /proc/loadavg: {}", err),
+                )
+            }
+        };
+        let cpus = std::thread::available_parallelism()
+            .map(|cpus| cpus.get() as f64)
+            .unwrap_or(1.0);
+        self.assess(&loadavg, cpus)
+    }
+
+    /// Rates the content of `/proc/loadavg` on a machine with `cpus` CPUs.
+    fn assess(&self, loadavg: &str, cpus: f64) -> ModuleOutput {
+        let Some([load1, load5, load15]) = parse_loadavg(loadavg) else {
+            return output(
+                ResultStatus::Critical,
+                format!("Unexpected /proc/loadavg content: {}", loadavg.trim()),
+            );
+        };
+        let cpus = if self.per_cpu { cpus } else { 1.0 };
+
+        let mut problems = Vec::new();
+        for (label, load, max) in [
+            ("load1", load1, self.max_load1),
+            ("load5", load5, self.max_load5),
+            ("load15", load15, self.max_load15),
+        ] {
+            if let Some(max) = max {
+                if load / cpus > max {
+                    problems.push(format!(
+                        "{} is {:.2}, more than {}",
+                        label,
+                        load / cpus,
+                        max
+                    ));
+                }
+            }
+        }
+
+        let mut module_output = verdict(
+            &problems,
+            format!("Load {:.2} {:.2} {:.2}", load1, load5, load15),
+        );
+        module_output.metrics.insert("load1".to_string(), load1);
+        module_output.metrics.insert("load5".to_string(), load5);
+        module_output.metrics.insert("load15".to_string(), load15);
+        module_output
+    }
+}
+
+impl ProcessCheck {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_none() && self.pidfile.is_none() {
+            return Err("Either name or pidfile is required".to_string());
+        }
+        if self.name.as_deref() == Some("") {
+            return Err("name must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let count = match (&self.pidfile, &self.name) {
+            (Some(pidfile), _) => match pidfile_running(pidfile) {
+                Ok(true) => 1,
+                Ok(false) => 0,
+                Err(err) => return output(ResultStatus::Critical, err),
+            },
+            (None, Some(name)) => match count_processes(name) {
+                Ok(val) => val,
+                Err(err) => return output(ResultStatus::Critical, err),
+            },
+            (None, None) => 0,
+        };
+
+        let subject = self
+            .pidfile
+            .as_deref()
+            .or(self.name.as_deref())
+            .unwrap_or_default();
+        let mut problems = Vec::new();
+        if count < self.min_count {
+            problems.push(format!(
+                "{} process(es) of {} running, expected at least {}",
+                count, subject, self.min_count
+            ));
+        }
+
+        let mut module_output = verdict(
+            &problems,
+            format!("{} process(es) of {} running", count, subject),
+        );
+        module_output
+            .metrics
+            .insert("processes".to_string(), count as f64);
+        module_output
+    }
+}
+
+/// Down with all problems joined, or ok with `message`.
+fn verdict(problems: &[String], message: String) -> ModuleOutput {
+    if problems.is_empty() {
+        output(ResultStatus::Ok, message)
+    } else {
+        output(ResultStatus::Critical, problems.join(", "))
+    }
+}
+
+fn percent(part: f64, total: f64) -> f64 {
+    if total == 0.0 {
+        return 100.0;
+    }
+    part / total * 100.0
+}
+
+fn statvfs(path: &str) -> Result<libc::statvfs, String> {
+    let path = CString::new(path).map_err(|err| err.to_string())?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(stats)
+}
+
+/// Value of a `/proc/meminfo` line in KiB.
+fn meminfo_value(meminfo: &str, key: &str) -> Option<f64> {
+    meminfo.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name != key {
+            return None;
+        }
+        value.split_whitespace().next()?.parse().ok()
+    })
+}
+
+/// The 1, 5 and 15 minute load averages at the start of `/proc/loadavg`.
+fn parse_loadavg(loadavg: &str) -> Option<[f64; 3]> {
+    let loads: Vec<f64> = loadavg
+        .split_whitespace()
+        .take(3)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    loads.try_into().ok()
+}
+
+fn pidfile_running(pidfile: &str) -> Result<bool, String> {
+    let content = std::fs::read_to_string(pidfile)
+        .map_err(|err| format!("Could not read {}: {}", pidfile, err))?;
+    let pid = parse_pid(&content).ok_or_else(|| format!("{} does not contain a pid", pidfile))?;
+    Ok(std::path::Path::new(&format!("/proc/{}", pid)).exists())
+}
+
+fn parse_pid(content: &str) -> Option<u32> {
+    content.trim().parse().ok()
+}
+
+fn count_processes(name: &str) -> Result<usize, String> {
+    let entries =
+        std::fs::read_dir("/proc").map_err(|err| format!("Could not read /proc: {}", err))?;
+
+    let mut count = 0;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(pid) = file_name.to_str() else {
+            continue;
+        };
+        if !pid.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        // Processes can exit between listing and reading, which counts as
+        // no match.
+        let comm = std::fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+        let cmdline = std::fs::read(entry.path().join("cmdline")).unwrap_or_default();
+        if process_name_matches(&comm, &String::from_utf8_lossy(&cmdline), name) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// `comm` only holds the first 15 bytes of the name, so longer names are
+/// also compared against the file name of `argv[0]`, the first entry of the
+/// NUL-separated `cmdline`.
+fn process_name_matches(comm: &str, cmdline: &str, name: &str) -> bool {
+    if comm.trim_end_matches('\n') == name {
+        return true;
+    }
+
+    let argv0 = cmdline.split('\0').next().unwrap_or_default();
+    let file_name = argv0.rsplit('/').next().unwrap_or_default();
+    file_name == name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = "MemTotal:       16000000 kB
+MemFree:          800000 kB
+MemAvailable:    4000000 kB
+Buffers:          100000 kB
+";
+
+    #[test]
+    fn meminfo_values_are_read_by_key() {
+        assert_eq!(meminfo_value(MEMINFO, "MemTotal"), Some(16_000_000.0));
+        assert_eq!(meminfo_value(MEMINFO, "MemAvailable"), Some(4_000_000.0));
+        assert_eq!(meminfo_value(MEMINFO, "Mem"), None);
+        assert_eq!(meminfo_value(MEMINFO, "SwapTotal"), None);
+    }
+
+    #[test]
+    fn available_memory_is_compared_in_percent() {
+        let output = MemoryCheck {
+            min_available_percent: 20.0,
+        }
+        .assess(MEMINFO);
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(output.metrics.get("available_percent"), Some(&25.0));
+        assert_eq!(
+            output.metrics.get("available_bytes"),
+            Some(&(4_000_000.0 * 1024.0))
+        );
+
+        let output = MemoryCheck {
+            min_available_percent: 30.0,
+        }
+        .assess(MEMINFO);
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+
+        let output = MemoryCheck {
+            min_available_percent: 30.0,
+        }
+        .assess("MemTotal: 100 kB\n");
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+    }
+
+    #[test]
+    fn loadavg_is_divided_by_cpus_only_per_cpu() {
+        let loadavg = "3.00 2.00 1.00 2/345 6789\n";
+        assert_eq!(parse_loadavg(loadavg), Some([3.0, 2.0, 1.0]));
+
+        let mut check = LoadCheck {
+            max_load1: Some(1.0),
+            max_load5: None,
+            max_load15: None,
+            per_cpu: false,
+        };
+        let output = check.assess(loadavg, 4.0);
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert_eq!(
+            output.message.as_deref(),
+            Some("load1 is 3.00, more than 1")
+        );
+
+        check.per_cpu = true;
+        let output = check.assess(loadavg, 4.0);
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(output.metrics.get("load1"), Some(&3.0));
+    }
+
+    #[test]
+    fn malformed_loadavg_is_critical() {
+        assert_eq!(parse_loadavg("1.0 nope"), None);
+
+        let check = LoadCheck {
+            max_load1: None,
+            max_load5: None,
+            max_load15: None,
+            per_cpu: false,
+        };
+        assert_eq!(check.assess("", 1.0).status, Some(ResultStatus::Critical));
+    }
+
+    #[test]
+    fn process_names_match_comm_or_argv0() {
+        assert!(process_name_matches("nginx\n", "", "nginx"));
+        assert!(!process_name_matches("nginx\n", "", "ngin"));
+
+        // The kernel truncates comm to 15 bytes.
+        let cmdline = "/usr/local/bin/health-check-agent\0--verbose\0";
+        assert!(process_name_matches(
+            "health-check-ag\n",
+            cmdline,
+            "health-check-agent"
+        ));
+        assert!(!process_name_matches(
+            "health-check-ag\n",
+            cmdline,
+            "--verbose"
+        ));
+        assert!(!process_name_matches("", "", "health-check-agent"));
+    }
+
+    #[test]
+    fn pidfile_points_at_running_process() {
+        let pidfile = std::env::temp_dir().join(format!("health-check-{}.pid", std::process::id()));
+        let pidfile_path = pidfile.to_str().unwrap();
+
+        std::fs::write(&pidfile, format!("{}\n", std::process::id())).unwrap();
+        assert_eq!(pidfile_running(pidfile_path), Ok(true));
+
+        // Above the kernel's pid limit.
+        std::fs::write(&pidfile, "99999999").unwrap();
+        assert_eq!(pidfile_running(pidfile_path), Ok(false));
+
+        std::fs::write(&pidfile, "nginx").unwrap();
+        assert!(pidfile_running(pidfile_path)
+            .unwrap_err()
+            .ends_with("does not contain a pid"));
+
+        std::fs::remove_file(&pidfile).unwrap();
+        assert!(pidfile_running(pidfile_path)
+            .unwrap_err()
+            .starts_with("Could not read"));
+    }
+
+    #[test]
+    fn process_check_requires_a_name_or_pidfile() {
+        let mut check = ProcessCheck {
+            name: None,
+            pidfile: None,
+            min_count: 1,
+        };
+        assert!(check.validate().is_err());
+
+        check.name = Some(String::new());
+        assert!(check.validate().is_err());
+    }
+
+    #[test]
+    fn disk_space_of_temp_dir_is_reported() {
+        let mut check = DiskCheck {
+            path: std::env::temp_dir().to_str().unwrap().to_string(),
+            min_free_percent: 0.0,
+            min_free_inodes_percent: None,
+        };
+
+        let output = check.run();
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert!(output.metrics["free_bytes"] > 0.0);
+        assert!((0.0..=100.0).contains(&output.metrics["free_percent"]));
+
+        check.min_free_percent = 101.0;
+        assert_eq!(check.run().status, Some(ResultStatus::Critical));
+
+        check.path = "/nonexistent/health-check".to_string();
+        let output = check.run();
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().starts_with("Could not stat"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::protocol::{ModuleOutput, ResultStatus};
+
+use super::output;
+
+/// Connects to a TCP port, optionally sends a payload and checks the start
+/// of the answer, e.g. an SMTP banner.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TcpCheck {
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Written right after connecting.
+    pub send: Option<String>,
+    /// Prefix the first bytes received have to start with.
+    pub expect: Option<String>,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+impl TcpCheck {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.is_empty() {
+            return Err("host must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> ModuleOutput {
+        let timeout = Duration::from_secs(self.timeout_secs);
+        let target = format!("{}:{}", self.host, self.port);
+
+        let started = std::time::Instant::now();
+        let mut stream = match super::connect(&self.host, self.port, timeout) {
+            Ok(val) => val,
+            Err(err) => return output(ResultStatus::Critical, err),
+        };
+        let connect_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let (status, message) = match self.exchange(&mut stream) {
+            Ok(_) => (
+                ResultStatus::Ok,
+                format!("{} accepted the connection", target),
+            ),
+            Err(err) => (ResultStatus::Critical, format!("{}: {}", target, err)),
+        };
+
+        let mut module_output = output(status, message);
+        module_output.latency_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+        module_output
+            .metrics
+            .insert("connect_ms".to_string(), connect_ms);
+        module_output
+    }
+
+    fn exchange(&self, stream: &mut TcpStream) -> Result<(), String> {
+        if let Some(send) = &self.send {
+            stream
+                .write_all(send.as_bytes())
+                .map_err(|err| format!("Could not send payload: {}", err))?;
+        }
+
+        let Some(expect) = &self.expect else {
+            return Ok(());
+        };
+
+        // Reads until the prefix is complete, the peer closes or the timeout
+        // hits.
+        let mut received = Vec::new();
+        let mut buffer = [0; 512];
+        while received.len() < expect.len() {
+            match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => received.extend_from_slice(&buffer[..read]),
+                Err(err) => {
+                    return Err(format!("Could not read response: {}", err));
+                }
+            }
+        }
+
+        if received.starts_with(expect.as_bytes()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Response `{}` does not start with `{}`",
+                String::from_utf8_lossy(&received).trim_end(),
+                expect
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    /// Accepts one connection, reads what the check sends and answers with
+    /// `banner`. Returns the port and the bytes received.
+    fn stub(banner: &'static [u8]) -> (u16, std::thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(200)))
+                .unwrap();
+            let mut received = [0; 64];
+            let read = stream.read(&mut received).unwrap_or_default();
+            stream.write_all(banner).unwrap();
+            received[..read].to_vec()
+        });
+        (port, server)
+    }
+
+    fn check(port: u16, send: Option<&str>, expect: Option<&str>) -> TcpCheck {
+        TcpCheck {
+            host: "127.0.0.1".to_string(),
+            port,
+            timeout_secs: 2,
+            send: send.map(str::to_string),
+            expect: expect.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn sends_payload_and_matches_banner() {
+        let (port, server) = stub(b"220 mail.example.com ESMTP\r\n");
+
+        let output = check(port, Some("EHLO health\r\n"), Some("220 ")).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert!(output.metrics.contains_key("connect_ms"));
+        assert_eq!(server.join().unwrap(), b"EHLO health\r\n");
+    }
+
+    #[test]
+    fn unexpected_banner_is_critical() {
+        let (port, server) = stub(b"-ERR go away\r\n");
+
+        let output = check(port, None, Some("+OK")).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("-ERR go away"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn refused_connection_is_critical() {
+        let port = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        let output = check(port, None, None).run();
+
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert!(output.message.unwrap().contains("Could not connect"));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{
+    checks::CheckConfig,
+    protocol::ResultStatus,
+    uptime::{self, Window},
+};
+
+/// Optional settings read from the TOML file at `CONFIG_PATH`.
+///
+/// ```toml
+/// uptime_windows = ["1h", "24h", "30d"]
+///
+/// [modules."weather_run.so"]
+/// timeout_secs = 10
+/// warning_latency_ms = 2000
+/// critical_latency_ms = 8000
+/// settings = { city = "Berlin" }
+///
+/// [modules."status_page.wasm"]
+/// allowed_hosts = ["status.example.com"]
+/// slo_target_percent = 99.9
+///
+/// [checks.homepage]
+/// kind = "http"
+/// url = "https://example.com"
+///
+/// [runners."report_run.wasm"]
+/// queue_policy = "coalesce"
+/// max_queue = 10
+/// max_concurrency = 2
+///
+/// [status_codes]
+/// warning = 200
+/// unknown = 503
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Built-in checks keyed by the name they are served under.
+    #[serde(default)]
+    pub checks: HashMap<String, CheckConfig>,
+    /// Settings of any module keyed by module file name.
+    #[serde(default)]
+    pub modules: HashMap<String, ModuleConfig>,
+    /// Runner settings keyed by module file name.
+    #[serde(default)]
+    pub runners: HashMap<String, RunnerConfig>,
+    /// HTTP status codes the health endpoints answer with.
+    #[serde(default)]
+    pub status_codes: StatusCodes,
+    /// Windows uptime is reported over, e.g. `["1h", "24h", "7d", "30d"]`.
+    #[serde(default = "uptime::default_windows")]
+    pub uptime_windows: Vec<Window>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            checks: HashMap::new(),
+            modules: HashMap::new(),
+            runners: HashMap::new(),
+            status_codes: StatusCodes::default(),
+            uptime_windows: uptime::default_windows(),
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn module(&self, module_name: &str) -> ModuleConfig {
+        self.modules.get(module_name).cloned().unwrap_or_default()
+    }
+
+    pub fn runner(&self, module_name: &str) -> RunnerConfig {
+        self.runners.get(module_name).cloned().unwrap_or_default()
+    }
+}
+
+/// Seconds an execution is given when no `timeout_secs` is configured.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// Free-form settings handed to the module itself as JSON.
+    #[serde(default)]
+    pub settings: toml::Table,
+    /// Time an execution is given, passed to the module as its deadline.
+    pub timeout_secs: Option<u64>,
+    /// Hosts a Wasm module may reach through `http_request`. None when empty.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// An execution slower than this degrades an ok status to warning.
+    pub warning_latency_ms: Option<u64>,
+    /// An execution slower than this turns an ok or warning status critical.
+    pub critical_latency_ms: Option<u64>,
+    /// Availability objective of a worker, e.g. 99.9, error budgets are
+    /// computed against.
+    pub slo_target_percent: Option<f64>,
+}
+
+impl ModuleConfig {
+    pub fn settings_json(&self) -> String {
+        serde_json::to_string(&self.settings).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        self.allowed_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    /// Applies the latency thresholds to the status of an execution.
+    pub fn degrade(&self, status: ResultStatus, duration_ms: f64) -> ResultStatus {
+        let exceeds = |limit: Option<u64>| limit.is_some_and(|limit| duration_ms > limit as f64);
+
+        if !status.is_up() {
+            status
+        } else if exceeds(self.critical_latency_ms) {
+            ResultStatus::Critical
+        } else if exceeds(self.warning_latency_ms) {
+            ResultStatus::Warning
+        } else {
+            status
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Every trigger is executed in arrival order.
+    #[default]
+    Fifo,
+    /// A trigger with the same payload as one still waiting joins it instead
+    /// of being queued again.
+    Coalesce,
+    /// A new trigger drops everything still waiting and cancels the runs
+    /// already executing.
+    CancelInFlight,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunnerConfig {
+    #[serde(default)]
+    pub queue_policy: QueuePolicy,
+    /// Maximum number of waiting triggers, further ones are rejected.
+    pub max_queue: Option<usize>,
+    /// Number of executions allowed to run at the same time. Native libraries
+    /// are shared between executions and must be thread-safe to use more than 1.
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        RunnerConfig {
+            queue_policy: QueuePolicy::default(),
+            max_queue: None,
+            max_concurrency: default_max_concurrency(),
+        }
+    }
+}
+
+fn default_max_concurrency() -> usize {
+    1
+}
+
+/// HTTP status code per [`ResultStatus`]. The status itself is always sent
+/// in the `x-health-status` header, so a degraded service answering 200 can
+/// still be told apart.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusCodes {
+    #[serde(default = "default_ok_code")]
+    pub ok: u16,
+    #[serde(default = "default_ok_code")]
+    pub warning: u16,
+    #[serde(default = "default_unavailable_code")]
+    pub critical: u16,
+    #[serde(default = "default_unavailable_code")]
+    pub unknown: u16,
+    #[serde(default = "default_crash_code")]
+    pub crash: u16,
+}
+
+impl Default for StatusCodes {
+    fn default() -> Self {
+        StatusCodes {
+            ok: default_ok_code(),
+            warning: default_ok_code(),
+            critical: default_unavailable_code(),
+            unknown: default_unavailable_code(),
+            crash: default_crash_code(),
+        }
+    }
+}
+
+impl StatusCodes {
+    pub fn code(&self, status: ResultStatus) -> u16 {
+        match status {
+            ResultStatus::Ok => self.ok,
+            ResultStatus::Warning => self.warning,
+            ResultStatus::Critical => self.critical,
+            ResultStatus::Unknown => self.unknown,
+            ResultStatus::Crash => self.crash,
+        }
+    }
+
+    /// Fails on the first code outside of 100 to 599.
+    pub fn validate(&self) -> Result<(), String> {
+        for status in ResultStatus::ALL {
+            let code = self.code(status);
+            if !(100..600).contains(&code) {
+                return Err(format!(
+                    "{} is not a valid HTTP status code for {}",
+                    code,
+                    status.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_ok_code() -> u16 {
+    200
+}
+
+fn default_unavailable_code() -> u16 {
+    503
+}
+
+fn default_crash_code() -> u16 {
+    500
+}
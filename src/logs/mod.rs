@@ -0,0 +1,152 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Default number of lines kept per module, override with `MODULE_LOG_LINES`.
+pub const DEFAULT_LOG_LINES: usize = 500;
+
+#[derive(Clone, Copy, Debug)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// Messages produced by the host about the module, e.g. load errors.
+    Host,
+}
+
+impl fmt::Display for LogStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogStream::Stdout => write!(f, "stdout"),
+            LogStream::Stderr => write!(f, "stderr"),
+            LogStream::Host => write!(f, "host"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    pub seq: u64,
+    pub timestamp: SystemTime,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            f,
+            "{}.{:03} [{}] {}",
+            timestamp.as_secs(),
+            timestamp.subsec_millis(),
+            self.stream,
+            self.line
+        )
+    }
+}
+
+/// Bounded ring buffer of the most recent output lines of one module.
+///
+/// Every line gets a monotonically increasing sequence number so readers
+/// following the log can ask for everything after the last line they saw.
+#[derive(Debug)]
+pub struct LogBuffer {
+    capacity: usize,
+    next_seq: u64,
+    lines: VecDeque<LogLine>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        LogBuffer {
+            capacity,
+            next_seq: 0,
+            lines: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, stream: LogStream, line: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(LogLine {
+            seq: self.next_seq,
+            timestamp: SystemTime::now(),
+            stream,
+            line: line.to_string(),
+        });
+        self.next_seq += 1;
+    }
+
+    /// The last `count` lines.
+    pub fn tail(&self, count: usize) -> Vec<LogLine> {
+        let skip = self.lines.len().saturating_sub(count);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+
+    /// All buffered lines with a sequence number of at least `seq`.
+    pub fn since(&self, seq: u64) -> Vec<LogLine> {
+        self.lines
+            .iter()
+            .filter(|line| line.seq >= seq)
+            .cloned()
+            .collect()
+    }
+
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+}
+
+/// The log buffers of all modules, shared between the scheduler and the API.
+#[derive(Clone)]
+pub struct ModuleLogs {
+    capacity: usize,
+    buffers: Arc<Mutex<HashMap<String, LogBuffer>>>,
+}
+
+impl ModuleLogs {
+    pub fn new(capacity: usize) -> Self {
+        ModuleLogs {
+            capacity,
+            buffers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Registers an empty buffer for a module so its logs endpoint exists
+    /// before the first execution.
+    pub fn register(&self, module_name: &str) {
+        if let Ok(mut buffers) = self.buffers.lock() {
+            buffers
+                .entry(module_name.to_string())
+                .or_insert_with(|| LogBuffer::new(self.capacity));
+        }
+    }
+
+    /// Appends every line of `text` to the module's buffer.
+    pub fn append(&self, module_name: &str, stream: LogStream, text: &str) {
+        if let Ok(mut buffers) = self.buffers.lock() {
+            if let Some(buffer) = buffers.get_mut(module_name) {
+                for line in text.lines() {
+                    buffer.push(stream, line);
+                }
+            }
+        }
+    }
+
+    /// Runs `read` against the module's buffer, `None` if the module is unknown.
+    pub fn read<T>(&self, module_name: &str, read: impl FnOnce(&LogBuffer) -> T) -> Option<T> {
+        let buffers = self.buffers.lock().ok()?;
+        buffers.get(module_name).map(read)
+    }
+}
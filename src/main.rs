@@ -1,7 +1,13 @@
 mod api;
+mod checks;
+mod config;
+mod logs;
+mod modules;
 mod persistency;
+mod protocol;
 mod threads;
 mod types;
+mod uptime;
 
 use std::{
     collections::HashMap,
@@ -10,8 +16,8 @@ use std::{
 };
 
 use indicatif::ProgressBar;
-use persistency::Save;
-use types::{DLLRunner, RunnerState, WasmRunner, WasmWorker, WorkerStates};
+use modules::{CheckModule, Host, ModuleMode, NativeModule, WasmModule};
+use types::ExitCodePolicy;
 
 #[macro_use]
 extern crate defer;
@@ -22,7 +28,9 @@ fn main() {
         ..Default::default()
       }));
 
-    let connection = sqlite::open(":memory:").expect("Could not create in memory db");
+    // History used for uptime reports only survives restarts with a file.
+    let database_path = std::env::var("DATABASE_PATH").unwrap_or(":memory:".to_string());
+    let connection = sqlite::open(&database_path).expect("Could not open database");
     let connection_mutex = Arc::new(Mutex::new(connection));
     let bar = ProgressBar::new_spinner();
     let modules_folder_path = match std::env::var("MODULES_PATH") {
@@ -39,6 +47,51 @@ fn main() {
         env_vars_string.push_str(&format!("{}={};;;", key, value));
     }
 
+    bar.set_message("Reading Wasm worker exit code policy");
+    let mut exit_code_policy = ExitCodePolicy::default();
+    if let Ok(val) = std::env::var("WASM_HEALTHY_EXIT_CODES") {
+        exit_code_policy.healthy = parse_exit_codes(&val, "WASM_HEALTHY_EXIT_CODES");
+    }
+    if let Ok(val) = std::env::var("WASM_WARNING_EXIT_CODES") {
+        exit_code_policy.warning = parse_exit_codes(&val, "WASM_WARNING_EXIT_CODES");
+    }
+    if let Ok(val) = std::env::var("WASM_UNHEALTHY_EXIT_CODES") {
+        exit_code_policy.unhealthy = parse_exit_codes(&val, "WASM_UNHEALTHY_EXIT_CODES");
+    }
+
+    bar.set_message("Reading configuration file");
+    let config = match std::env::var("CONFIG_PATH") {
+        Ok(val) => {
+            let text = match std::fs::read_to_string(&val) {
+                Ok(val) => val,
+                Err(_) => {
+                    panic!("Error: Could not read CONFIG_PATH file");
+                }
+            };
+            match config::Config::parse(&text) {
+                Ok(val) => val,
+                Err(err) => {
+                    panic!("Error: Could not parse CONFIG_PATH file: {}", err);
+                }
+            }
+        }
+        Err(_) => config::Config::default(),
+    };
+    if let Err(err) = config.status_codes.validate() {
+        panic!("Error: Invalid status_codes in CONFIG_PATH file: {}", err);
+    }
+
+    let module_log_lines = match std::env::var("MODULE_LOG_LINES") {
+        Ok(val) => match val.parse() {
+            Ok(val) => val,
+            Err(_) => {
+                panic!("Error: MODULE_LOG_LINES is not a valid number");
+            }
+        },
+        Err(_) => logs::DEFAULT_LOG_LINES,
+    };
+    let module_logs = logs::ModuleLogs::new(module_log_lines);
+
     bar.set_message("Checking if MODULES_PATH folder exists");
     match std::fs::exists(&modules_folder_path) {
         Ok(val) => {
@@ -58,10 +111,10 @@ fn main() {
         }
     };
 
-    let mut wasm_containers: Vec<WasmWorker> = Vec::new();
-    let mut wasm_run_containers: Vec<WasmRunner> = Vec::new();
-    let mut dll_run_containers: Vec<DLLRunner> = Vec::new();
-    let mut dll_containers: Vec<DLLRunner> = Vec::new();
+    let mut wasm_containers: Vec<Box<dyn CheckModule>> = Vec::new();
+    let mut wasm_run_containers: Vec<Box<dyn CheckModule>> = Vec::new();
+    let mut dll_run_containers: Vec<Box<dyn CheckModule>> = Vec::new();
+    let mut dll_containers: Vec<Box<dyn CheckModule>> = Vec::new();
     bar.set_message("Reading files in MODULES_PATH folder");
     for entry in modules_path_iterator {
         let entry = entry.expect("Error: Could not read entry in MODULES_PATH folder");
@@ -82,32 +135,72 @@ fn main() {
             "Reading {} file...",
             entry.file_name().to_str().unwrap()
         ));
-        if entry.file_name().to_str().unwrap().ends_with("_run.wasm") {
-            wasm_run_containers.push(WasmRunner {
-                module_name: entry.file_name().to_str().unwrap().to_string(),
-                bytes: std::fs::read(entry_path)
+        let module_name = entry.file_name().to_str().unwrap().to_string();
+        if module_name.ends_with("_run.wasm") {
+            let module_config = config.module(&module_name);
+            wasm_run_containers.push(Box::new(WasmModule::new(
+                module_name,
+                std::fs::read(entry_path)
                     .expect("Error: Could not read file in MODULES_PATH folder"),
-            });
-        } else if entry.file_name().to_str().unwrap().ends_with(".wasm") {
-            wasm_containers.push(WasmWorker {
-                module_name: entry.file_name().to_str().unwrap().to_string(),
-                bytes: std::fs::read(entry_path)
+                Host::new(connection_mutex.clone()),
+                module_config,
+            )));
+        } else if module_name.ends_with(".wasm") {
+            let module_config = config.module(&module_name);
+            wasm_containers.push(Box::new(WasmModule::new(
+                module_name,
+                std::fs::read(entry_path)
                     .expect("Error: Could not read file in MODULES_PATH folder"),
-            });
-        } else if entry.file_name().to_str().unwrap().ends_with("_run.so") {
-            dll_run_containers.push(DLLRunner {
-                module_name: entry.file_name().to_str().unwrap().to_string(),
-                path: canonicalize(entry_path).unwrap().display().to_string(),
-            });
-        } else if entry.file_name().to_str().unwrap().ends_with(".so") {
-            dll_containers.push(DLLRunner {
-                module_name: entry.file_name().to_str().unwrap().to_string(),
-                path: canonicalize(entry_path).unwrap().display().to_string(),
-            });
+                Host::new(connection_mutex.clone()),
+                module_config,
+            )));
+        } else if module_name.ends_with("_run.so") {
+            let module_config = config.module(&module_name);
+            dll_run_containers.push(Box::new(NativeModule::new(
+                module_name,
+                canonicalize(entry_path).unwrap().display().to_string(),
+                ModuleMode::Runner,
+                env_vars_string.clone(),
+                Host::new(connection_mutex.clone()),
+                module_config,
+            )));
+        } else if module_name.ends_with(".so") {
+            let module_config = config.module(&module_name);
+            dll_containers.push(Box::new(NativeModule::new(
+                module_name,
+                canonicalize(entry_path).unwrap().display().to_string(),
+                ModuleMode::Worker,
+                env_vars_string.clone(),
+                Host::new(connection_mutex.clone()),
+                module_config,
+            )));
+        }
+    }
+
+    let mut builtin_checks: Vec<Box<dyn CheckModule>> = Vec::new();
+    for (name, check) in config.checks.iter() {
+        builtin_checks.push(Box::new(checks::BuiltinCheck::new(
+            name.clone(),
+            check.clone(),
+        )));
+    }
+
+    // Built-in checks share the worker states with the Wasm workers, so a
+    // name used by both would hide one of them.
+    for check in builtin_checks.iter() {
+        if wasm_containers
+            .iter()
+            .any(|module| module.name() == check.name())
+        {
+            panic!(
+                "Error: Built-in check {} has the same name as a Wasm module",
+                check.name()
+            );
         }
     }
 
     if wasm_containers.is_empty()
+        && builtin_checks.is_empty()
         && wasm_run_containers.is_empty()
         && dll_run_containers.is_empty()
         && dll_containers.is_empty()
@@ -124,19 +217,23 @@ fn main() {
     if modules_folder_path {
         bar.set_message("Printing modules");
         for entry in wasm_containers.iter() {
-            println!("Wasm module: {}", entry.module_name);
+            println!("Wasm module: {}", entry.name());
+        }
+
+        for entry in builtin_checks.iter() {
+            println!("Built-in check: {}", entry.name());
         }
 
         for entry in wasm_run_containers.iter() {
-            println!("Wasm runner: {}", entry.module_name);
+            println!("Wasm runner: {}", entry.name());
         }
 
         for entry in dll_run_containers.iter() {
-            println!("DLL runner: {}", entry.module_name);
+            println!("DLL runner: {}", entry.name());
         }
 
         for entry in dll_containers.iter() {
-            println!("DLL module: {}", entry.module_name);
+            println!("DLL module: {}", entry.name());
         }
     }
 
@@ -147,18 +244,46 @@ fn main() {
     let runner_states = Arc::new(Mutex::new(HashMap::new()));
     let native_states = Arc::new(Mutex::new(HashMap::new()));
 
-    threads::spawn_wasm_worker_threads(wasm_containers, worker_states.clone());
-    threads::spawn_dll_worker_threads(dll_containers, native_worker_states.clone());
-    threads::spawn_wasm_runner_threads(
+    threads::spawn_worker_threads(
+        wasm_containers,
+        worker_states.clone(),
+        connection_mutex.clone(),
+        module_logs.clone(),
+        exit_code_policy.clone(),
+        &config,
+    );
+    // Built-in checks are served next to the Wasm workers under /health.
+    threads::spawn_worker_threads(
+        builtin_checks,
+        worker_states.clone(),
+        connection_mutex.clone(),
+        module_logs.clone(),
+        exit_code_policy.clone(),
+        &config,
+    );
+    threads::spawn_worker_threads(
+        dll_containers,
+        native_worker_states.clone(),
+        connection_mutex.clone(),
+        module_logs.clone(),
+        exit_code_policy.clone(),
+        &config,
+    );
+    threads::spawn_runner_threads(
         wasm_run_containers,
         runner_states.clone(),
         connection_mutex.clone(),
+        module_logs.clone(),
+        exit_code_policy.clone(),
+        &config,
     );
-    threads::spawn_dll_runner_threads(
+    threads::spawn_runner_threads(
         dll_run_containers,
         native_states.clone(),
         connection_mutex.clone(),
-        env_vars_string,
+        module_logs.clone(),
+        exit_code_policy,
+        &config,
     );
 
     std::thread::spawn(move || {
@@ -167,8 +292,27 @@ fn main() {
             native_worker_states,
             runner_states,
             native_states,
+            connection_mutex,
+            module_logs,
+            config,
         );
     });
 
     std::thread::park();
 }
+
+/// Parses a comma separated list of exit codes, e.g. `0,2`.
+fn parse_exit_codes(value: &str, variable: &str) -> Vec<i32> {
+    value
+        .split(',')
+        .map(|code| match code.trim().parse() {
+            Ok(val) => val,
+            Err(_) => {
+                panic!(
+                    "Error: {} contains an invalid exit code: {}",
+                    variable, code
+                );
+            }
+        })
+        .collect()
+}
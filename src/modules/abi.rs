@@ -0,0 +1,281 @@
+//! Rust side of the context ABI declared in `include/health_check.h`.
+
+use std::{
+    collections::HashMap,
+    ffi::{c_void, CStr, CString},
+    os::raw::c_char,
+    sync::Mutex,
+};
+
+use super::host::Host;
+
+/// First ABI version whose `start` receives an [`HcContext`].
+pub const CONTEXT_ABI_VERSION: u32 = 2;
+
+pub type LogFn = unsafe extern "C" fn(host_data: *mut c_void, level: i32, message: *const c_char);
+pub type KvGetFn =
+    unsafe extern "C" fn(host_data: *mut c_void, key: *const c_char) -> *const c_char;
+pub type KvSetFn =
+    unsafe extern "C" fn(host_data: *mut c_void, key: *const c_char, value: *const c_char) -> i32;
+pub type EmitMetricFn =
+    unsafe extern "C" fn(host_data: *mut c_void, name: *const c_char, value: f64);
+
+/// Mirrors `hc_context`.
+#[repr(C)]
+pub struct HcContext {
+    pub abi_version: u32,
+    pub module_name: *const c_char,
+    pub config: *const c_char,
+    pub payload: *const c_char,
+    pub env: *const c_char,
+    pub deadline_unix_ms: u64,
+    pub host_data: *mut c_void,
+    pub log: LogFn,
+    pub kv_get: KvGetFn,
+    pub kv_set: KvSetFn,
+    pub emit_metric: EmitMetricFn,
+}
+
+/// What the callbacks of one `start` call collected. The module may call
+/// back from its own threads, hence the lock.
+pub struct CallState {
+    host: Host,
+    inner: Mutex<CallInner>,
+}
+
+#[derive(Default)]
+struct CallInner {
+    log: String,
+    metrics: HashMap<String, f64>,
+    /// Values handed out by `kv_get`, kept alive until `start` returns.
+    strings: Vec<CString>,
+}
+
+impl CallState {
+    pub fn new(host: Host) -> Self {
+        CallState {
+            host,
+            inner: Mutex::new(CallInner::default()),
+        }
+    }
+
+    /// Builds the context for a `start` call. The returned struct borrows
+    /// `self` and the given strings through raw pointers, so all of them
+    /// must outlive the call.
+    pub fn context(
+        &self,
+        module_name: &CStr,
+        config: &CStr,
+        payload: &CStr,
+        env: &CStr,
+        deadline_unix_ms: u64,
+    ) -> HcContext {
+        HcContext {
+            abi_version: CONTEXT_ABI_VERSION,
+            module_name: module_name.as_ptr(),
+            config: config.as_ptr(),
+            payload: payload.as_ptr(),
+            env: env.as_ptr(),
+            deadline_unix_ms,
+            host_data: self as *const CallState as *mut c_void,
+            log: host_log,
+            kv_get: host_kv_get,
+            kv_set: host_kv_set,
+            emit_metric: host_emit_metric,
+        }
+    }
+
+    /// The log lines and metrics reported during the call.
+    pub fn finish(self) -> (String, HashMap<String, f64>) {
+        let inner = match self.inner.into_inner() {
+            Ok(val) => val,
+            Err(err) => err.into_inner(),
+        };
+        (inner.log, inner.metrics)
+    }
+}
+
+unsafe fn call_state<'a>(host_data: *mut c_void) -> Option<&'a CallState> {
+    (host_data as *const CallState).as_ref()
+}
+
+unsafe fn read_string(value: *const c_char) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(value).to_string_lossy().to_string())
+}
+
+unsafe extern "C" fn host_log(host_data: *mut c_void, level: i32, message: *const c_char) {
+    let (Some(state), Some(message)) = (call_state(host_data), read_string(message)) else {
+        return;
+    };
+    let level = match level {
+        0 => "DEBUG",
+        1 => "INFO",
+        2 => "WARN",
+        _ => "ERROR",
+    };
+
+    if let Ok(mut inner) = state.inner.lock() {
+        for line in message.lines() {
+            inner.log.push_str(&format!("[{}] {}\n", level, line));
+        }
+    }
+}
+
+unsafe extern "C" fn host_kv_get(host_data: *mut c_void, key: *const c_char) -> *const c_char {
+    let (Some(state), Some(key)) = (call_state(host_data), read_string(key)) else {
+        return std::ptr::null();
+    };
+    let Some(value) = state.host.kv_get(&key) else {
+        return std::ptr::null();
+    };
+    let Ok(value) = CString::new(value) else {
+        return std::ptr::null();
+    };
+
+    match state.inner.lock() {
+        Ok(mut inner) => {
+            // The heap buffer of a CString does not move with the CString.
+            let pointer = value.as_ptr();
+            inner.strings.push(value);
+            pointer
+        }
+        Err(_) => std::ptr::null(),
+    }
+}
+
+unsafe extern "C" fn host_kv_set(
+    host_data: *mut c_void,
+    key: *const c_char,
+    value: *const c_char,
+) -> i32 {
+    let (Some(state), Some(key), Some(value)) =
+        (call_state(host_data), read_string(key), read_string(value))
+    else {
+        return -1;
+    };
+
+    if state.host.kv_set(&key, &value) {
+        0
+    } else {
+        -1
+    }
+}
+
+unsafe extern "C" fn host_emit_metric(host_data: *mut c_void, name: *const c_char, value: f64) {
+    let (Some(state), Some(name)) = (call_state(host_data), read_string(name)) else {
+        return;
+    };
+
+    if let Ok(mut inner) = state.inner.lock() {
+        inner.metrics.insert(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn call_state() -> CallState {
+        let connection = sqlite::open(":memory:").unwrap();
+        CallState::new(Host::new(Arc::new(Mutex::new(connection))))
+    }
+
+    fn context(state: &CallState) -> HcContext {
+        state.context(c"module", c"{}", c"", c"", 0)
+    }
+
+    #[test]
+    fn kv_set_then_get_round_trips() {
+        let state = call_state();
+        let ctx = context(&state);
+
+        unsafe {
+            assert_eq!(
+                (ctx.kv_set)(ctx.host_data, c"city".as_ptr(), c"Berlin".as_ptr()),
+                0
+            );
+            let value = (ctx.kv_get)(ctx.host_data, c"city".as_ptr());
+            assert_eq!(CStr::from_ptr(value), c"Berlin");
+        }
+    }
+
+    #[test]
+    fn kv_get_values_stay_valid_until_the_call_ends() {
+        let state = call_state();
+        let ctx = context(&state);
+
+        unsafe {
+            (ctx.kv_set)(ctx.host_data, c"key".as_ptr(), c"first".as_ptr());
+            let first = (ctx.kv_get)(ctx.host_data, c"key".as_ptr());
+            (ctx.kv_set)(ctx.host_data, c"key".as_ptr(), c"second".as_ptr());
+            let second = (ctx.kv_get)(ctx.host_data, c"key".as_ptr());
+
+            assert_eq!(CStr::from_ptr(first), c"first");
+            assert_eq!(CStr::from_ptr(second), c"second");
+        }
+    }
+
+    #[test]
+    fn kv_get_of_missing_key_is_null() {
+        let state = call_state();
+        let ctx = context(&state);
+
+        unsafe {
+            assert!((ctx.kv_get)(ctx.host_data, c"missing".as_ptr()).is_null());
+        }
+    }
+
+    #[test]
+    fn kv_set_rejects_null_arguments() {
+        let state = call_state();
+        let ctx = context(&state);
+
+        unsafe {
+            assert_eq!(
+                (ctx.kv_set)(ctx.host_data, c"key".as_ptr(), std::ptr::null()),
+                -1
+            );
+            assert_eq!(
+                (ctx.kv_set)(std::ptr::null_mut(), c"key".as_ptr(), c"value".as_ptr()),
+                -1
+            );
+        }
+    }
+
+    #[test]
+    fn emitted_metrics_keep_the_last_value() {
+        let state = call_state();
+        let ctx = context(&state);
+
+        unsafe {
+            (ctx.emit_metric)(ctx.host_data, c"latency_ms".as_ptr(), 12.0);
+            (ctx.emit_metric)(ctx.host_data, c"latency_ms".as_ptr(), 15.5);
+            (ctx.emit_metric)(ctx.host_data, std::ptr::null(), 1.0);
+        }
+
+        let (_, metrics) = state.finish();
+        assert_eq!(metrics, HashMap::from([("latency_ms".to_string(), 15.5)]));
+    }
+
+    #[test]
+    fn log_prefixes_every_line_and_replaces_invalid_utf8() {
+        let state = call_state();
+        let ctx = context(&state);
+
+        unsafe {
+            (ctx.log)(ctx.host_data, 1, c"first\nsecond".as_ptr());
+            (ctx.log)(ctx.host_data, 7, c"bad \xff byte".as_ptr());
+        }
+
+        let (log, _) = state.finish();
+        assert_eq!(
+            log,
+            "[INFO] first\n[INFO] second\n[ERROR] bad \u{FFFD} byte\n"
+        );
+    }
+}
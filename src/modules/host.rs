@@ -0,0 +1,36 @@
+use std::sync::{Arc, Mutex};
+
+use sqlite::Connection;
+
+use crate::persistency::{KeyValuePair, Save};
+
+/// Services the host offers to modules while they execute.
+#[derive(Clone)]
+pub struct Host {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Host {
+    pub fn new(connection: Arc<Mutex<Connection>>) -> Self {
+        Host { connection }
+    }
+
+    pub fn kv_get(&self, key: &str) -> Option<String> {
+        let connection = self.connection.lock().ok()?;
+        KeyValuePair::load(&connection, key).ok().flatten()
+    }
+
+    pub fn kv_set(&self, key: &str, value: &str) -> bool {
+        let connection = match self.connection.lock() {
+            Ok(val) => val,
+            Err(_) => return false,
+        };
+
+        KeyValuePair {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+        .persist(&connection)
+        .is_ok()
+    }
+}
@@ -0,0 +1,240 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ModuleConfig;
+
+/// Request a Wasm module asks the host to send, passed as JSON.
+#[derive(Debug, Deserialize)]
+pub struct HttpRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+    pub timeout_ms: Option<u64>,
+}
+
+/// Answer handed back to the module as JSON. Error statuses are responses
+/// too; `error` is only set when no response was received at all.
+#[derive(Debug, Default, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HttpResponse {
+    fn error(message: String) -> Self {
+        HttpResponse {
+            error: Some(message),
+            ..Default::default()
+        }
+    }
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// Sends the request if its host is allowlisted for the module. Redirects are
+/// not followed, since they could lead to a host that is not allowed.
+pub fn send(request: &HttpRequest, module_config: &ModuleConfig) -> HttpResponse {
+    let timeout = match request.timeout_ms {
+        Some(val) => Duration::from_millis(val).min(module_config.timeout()),
+        None => module_config.timeout(),
+    };
+    let agent = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .redirects(0)
+        .build();
+
+    let mut builder = agent.request(&request.method, &request.url);
+    let host = match builder.request_url() {
+        Ok(val) => val.host().to_string(),
+        Err(err) => return HttpResponse::error(format!("Invalid URL: {}", err)),
+    };
+    if !module_config.allows_host(&host) {
+        return HttpResponse::error(format!("Host {} is not allowed", host));
+    }
+
+    for (key, value) in request.headers.iter() {
+        builder = builder.set(key, value);
+    }
+
+    let result = if request.body.is_empty() {
+        builder.call()
+    } else {
+        builder.send_string(&request.body)
+    };
+    let response = match result {
+        Ok(val) => val,
+        Err(ureq::Error::Status(_, val)) => val,
+        Err(err) => return HttpResponse::error(err.to_string()),
+    };
+
+    let status = response.status();
+    let mut headers = HashMap::new();
+    for name in response.headers_names() {
+        if let Some(value) = response.header(&name) {
+            headers.insert(name, value.to_string());
+        }
+    }
+
+    match response.into_string() {
+        Ok(body) => HttpResponse {
+            status,
+            headers,
+            body,
+            error: None,
+        },
+        Err(err) => HttpResponse::error(format!("Could not read response body: {}", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{ErrorKind, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+        time::Instant,
+    };
+
+    use super::*;
+
+    /// Answers a single request on 127.0.0.1 with `response`, handing back
+    /// the request the stub received.
+    fn serve_once(response: String) -> (u16, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0; 4096];
+            let read = stream.read(&mut buffer).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_string()
+        });
+        (port, handle)
+    }
+
+    fn assert_no_connection(listener: &TcpListener) {
+        listener.set_nonblocking(true).unwrap();
+        let err = listener.accept().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    }
+
+    fn request(url: String) -> HttpRequest {
+        HttpRequest {
+            method: default_method(),
+            url,
+            headers: HashMap::new(),
+            body: String::new(),
+            timeout_ms: None,
+        }
+    }
+
+    fn allowing(hosts: &[&str]) -> ModuleConfig {
+        ModuleConfig {
+            allowed_hosts: hosts.iter().map(|host| host.to_string()).collect(),
+            timeout_secs: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn allowed_host_returns_the_response() {
+        let (port, stub) = serve_once(
+            "HTTP/1.1 503 Service Unavailable\r\nX-Check: down\r\nContent-Length: 4\r\n\r\nbusy"
+                .to_string(),
+        );
+        let mut request = request(format!("http://127.0.0.1:{}/status", port));
+        request.method = "POST".to_string();
+        request.body = "ping".to_string();
+
+        let response = send(&request, &allowing(&["127.0.0.1"]));
+
+        assert_eq!(response.error, None);
+        assert_eq!(response.status, 503);
+        assert_eq!(
+            response.headers.get("x-check").map(String::as_str),
+            Some("down")
+        );
+        assert_eq!(response.body, "busy");
+        assert!(stub.join().unwrap().starts_with("POST /status HTTP/1.1"));
+    }
+
+    #[test]
+    fn other_hosts_are_rejected_before_connecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let response = send(
+            &request(format!("http://127.0.0.1:{}/", port)),
+            &allowing(&["localhost"]),
+        );
+        assert_eq!(
+            response.error.as_deref(),
+            Some("Host 127.0.0.1 is not allowed")
+        );
+
+        let response = send(
+            &request(format!("http://LOCALHOST:{}/", port)),
+            &allowing(&["localhost.example.com"]),
+        );
+        assert_eq!(
+            response.error.as_deref(),
+            Some("Host localhost is not allowed")
+        );
+
+        assert_no_connection(&listener);
+    }
+
+    #[test]
+    fn allowlist_ignores_case_but_not_suffixes() {
+        let config = allowing(&["status.example.com"]);
+
+        assert!(config.allows_host("Status.Example.COM"));
+        assert!(!config.allows_host("status.example.com.evil.org"));
+        assert!(!config.allows_host("example.com"));
+    }
+
+    #[test]
+    fn redirects_are_not_followed() {
+        let target = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target_port = target.local_addr().unwrap().port();
+        let response = format!(
+            "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{}/\r\nContent-Length: 0\r\n\r\n",
+            target_port
+        );
+        let (port, stub) = serve_once(response);
+
+        let response = send(
+            &request(format!("http://127.0.0.1:{}/", port)),
+            &allowing(&["127.0.0.1"]),
+        );
+        stub.join().unwrap();
+
+        assert_eq!(response.status, 302);
+        assert_no_connection(&target);
+    }
+
+    #[test]
+    fn request_timeout_is_honoured() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut request = request(format!("http://127.0.0.1:{}/", port));
+        request.timeout_ms = Some(200);
+
+        let started = Instant::now();
+        let response = send(&request, &allowing(&["127.0.0.1"]));
+
+        assert!(response.error.is_some());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        drop(listener);
+    }
+}
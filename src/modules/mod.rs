@@ -0,0 +1,68 @@
+mod abi;
+mod host;
+mod http;
+mod native;
+mod wasm;
+mod wasm_host;
+
+pub use host::Host;
+pub use native::NativeModule;
+pub use wasm::WasmModule;
+
+use std::collections::HashMap;
+
+use crate::{
+    protocol::{self, ModuleOutput},
+    types::RunPayload,
+};
+
+/// Whether a module is polled periodically or executed on demand.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModuleMode {
+    Worker,
+    Runner,
+}
+
+/// The raw result of executing a module once.
+pub struct Execution {
+    /// Process exit code, only known for backends that have one (Wasm).
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Metrics reported through host callbacks rather than the output.
+    pub metrics: HashMap<String, f64>,
+}
+
+/// Stops the execution in progress of the module instance it was taken from.
+pub type Interrupt = Box<dyn FnOnce() + Send>;
+
+/// A health-check backend the scheduler can drive.
+///
+/// `load` is called once on the module's own thread before the first
+/// execution; `execute` is then called for every scheduled or triggered run,
+/// with the payload of the thunder request that triggered it, if any.
+pub trait CheckModule: Send {
+    fn name(&self) -> &str;
+
+    /// A new, not yet loaded instance of the same module, used to run
+    /// several executions of a runner in parallel and to replace an instance
+    /// whose execution was cancelled.
+    fn fresh_instance(&self) -> Box<dyn CheckModule>;
+
+    fn load(&mut self) -> Result<(), String>;
+
+    fn execute(&mut self, payload: Option<&RunPayload>) -> Result<Execution, String>;
+
+    /// Returns a handle that stops the next or current execution, for
+    /// backends able to interrupt one. Without it a cancelled execution is
+    /// left to finish in the background.
+    fn interrupt(&self) -> Option<Interrupt> {
+        None
+    }
+
+    fn parse(&self, execution: &Execution) -> ModuleOutput {
+        let mut output = protocol::parse_output(&execution.stdout);
+        output.metrics.extend(execution.metrics.clone());
+        output
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+};
+
+use libloading::Library;
+
+use crate::{config::ModuleConfig, types::RunPayload};
+
+use super::{
+    abi::{CallState, HcContext, CONTEXT_ABI_VERSION},
+    host::Host,
+    CheckModule, Execution, ModuleMode,
+};
+
+type WorkerStartFn = unsafe extern "C" fn() -> *const c_char;
+type RunnerStartFn = unsafe extern "C" fn(env: *const c_char) -> *const c_char;
+type PayloadRunnerStartFn =
+    unsafe extern "C" fn(env: *const c_char, payload: *const c_char) -> *const c_char;
+type ContextStartFn = unsafe extern "C" fn(context: *const HcContext) -> *const c_char;
+type FreeStringFn = unsafe extern "C" fn(*const c_char);
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+
+/// ABI versions this host can drive. Libraries without a
+/// `health_check_abi_version` export are assumed to be version 1.
+pub const SUPPORTED_ABI_VERSIONS: &[u32] = &[1, CONTEXT_ABI_VERSION];
+
+/// Exported by ABI 1 runners whose `start` takes the payload JSON as a
+/// second argument. Runners without it only get the environment string.
+const PAYLOAD_MARKER_SYMBOL: &[u8] = b"health_check_runner_payload";
+
+/// The `start` export, whose signature depends on the ABI version and, for
+/// version 1, on the module mode and the payload marker.
+#[derive(Clone, Copy)]
+enum StartFn {
+    Worker(WorkerStartFn),
+    Runner(RunnerStartFn),
+    PayloadRunner(PayloadRunnerStartFn),
+    Context(ContextStartFn),
+}
+
+/// Which signature of `start` a library is called with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StartKind {
+    Worker,
+    Runner,
+    PayloadRunner,
+    Context,
+}
+
+/// Resolved exports of a loaded library. The function pointers stay valid for
+/// as long as `_library` is kept alive next to them.
+struct LoadedLibrary {
+    start: StartFn,
+    free_string: FreeStringFn,
+    _library: Library,
+}
+
+pub struct NativeModule {
+    module_name: String,
+    path: String,
+    mode: ModuleMode,
+    env_vars_string: String,
+    host: Host,
+    module_config: ModuleConfig,
+    library: Option<LoadedLibrary>,
+}
+
+impl NativeModule {
+    pub fn new(
+        module_name: String,
+        path: String,
+        mode: ModuleMode,
+        env_vars_string: String,
+        host: Host,
+        module_config: ModuleConfig,
+    ) -> Self {
+        NativeModule {
+            module_name,
+            path,
+            mode,
+            env_vars_string,
+            host,
+            module_config,
+            library: None,
+        }
+    }
+
+    /// Environment string with the payload env entries appended, and the
+    /// payload as JSON (empty without a payload).
+    fn call_arguments(&self, payload: Option<&RunPayload>) -> Result<(CString, CString), String> {
+        let mut env_vars_string = self.env_vars_string.clone();
+        let mut payload_json = String::new();
+        if let Some(payload) = payload {
+            for (key, value) in payload.env.iter() {
+                env_vars_string.push_str(&format!("{}={};;;", key, value));
+            }
+            payload_json = serde_json::to_string(payload)
+                .map_err(|err| format!("Could not serialize payload: {}", err))?;
+        }
+
+        let env_vars_string = CString::new(env_vars_string)
+            .map_err(|err| format!("Invalid environment string: {}", err))?;
+        let payload_json =
+            CString::new(payload_json).map_err(|err| format!("Invalid payload: {}", err))?;
+        Ok((env_vars_string, payload_json))
+    }
+}
+
+impl CheckModule for NativeModule {
+    fn name(&self) -> &str {
+        &self.module_name
+    }
+
+    fn fresh_instance(&self) -> Box<dyn CheckModule> {
+        Box::new(NativeModule::new(
+            self.module_name.clone(),
+            self.path.clone(),
+            self.mode,
+            self.env_vars_string.clone(),
+            self.host.clone(),
+            self.module_config.clone(),
+        ))
+    }
+
+    fn load(&mut self) -> Result<(), String> {
+        let library = unsafe { Library::new(&self.path) }
+            .map_err(|err| format!("Could not load library: {}", err))?;
+
+        let abi_version = match unsafe { library.get::<AbiVersionFn>(b"health_check_abi_version") }
+        {
+            Ok(symbol) => unsafe { symbol() },
+            Err(_) => 1,
+        };
+        let accepts_payload = unsafe { library.get::<*const u8>(PAYLOAD_MARKER_SYMBOL) }.is_ok();
+
+        let start = unsafe {
+            match start_kind(self.mode, abi_version, accepts_payload)? {
+                StartKind::Context => library
+                    .get::<ContextStartFn>(b"start")
+                    .map(|symbol| StartFn::Context(*symbol)),
+                StartKind::Worker => library
+                    .get::<WorkerStartFn>(b"start")
+                    .map(|symbol| StartFn::Worker(*symbol)),
+                StartKind::PayloadRunner => library
+                    .get::<PayloadRunnerStartFn>(b"start")
+                    .map(|symbol| StartFn::PayloadRunner(*symbol)),
+                StartKind::Runner => library
+                    .get::<RunnerStartFn>(b"start")
+                    .map(|symbol| StartFn::Runner(*symbol)),
+            }
+        }
+        .map_err(|_| missing_symbol("start", self.mode, abi_version))?;
+
+        let free_string = unsafe { library.get::<FreeStringFn>(b"free_string") }
+            .map(|symbol| *symbol)
+            .map_err(|_| missing_symbol("free_string", self.mode, abi_version))?;
+
+        self.library = Some(LoadedLibrary {
+            start,
+            free_string,
+            _library: library,
+        });
+        Ok(())
+    }
+
+    fn execute(&mut self, payload: Option<&RunPayload>) -> Result<Execution, String> {
+        let library = match &self.library {
+            Some(val) => val,
+            None => return Err("Library is not loaded".to_string()),
+        };
+
+        let mut call_state = None;
+        let result = match library.start {
+            StartFn::Worker(start) => unsafe { start() },
+            StartFn::Runner(start) => {
+                let (env_vars_string, _) = self.call_arguments(payload)?;
+                unsafe { start(env_vars_string.as_ptr()) }
+            }
+            StartFn::PayloadRunner(start) => {
+                let (env_vars_string, payload_json) = self.call_arguments(payload)?;
+                unsafe { start(env_vars_string.as_ptr(), payload_json.as_ptr()) }
+            }
+            StartFn::Context(start) => {
+                let (env_vars_string, payload_json) = self.call_arguments(payload)?;
+                let module_name = CString::new(self.module_name.as_str())
+                    .map_err(|err| format!("Invalid module name: {}", err))?;
+                let config = CString::new(self.module_config.settings_json())
+                    .map_err(|err| format!("Invalid module settings: {}", err))?;
+                let deadline = std::time::SystemTime::now() + self.module_config.timeout();
+                let deadline_unix_ms = deadline
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|duration| duration.as_millis() as u64)
+                    .unwrap_or_default();
+
+                let state = call_state.insert(CallState::new(self.host.clone()));
+                let context = state.context(
+                    &module_name,
+                    &config,
+                    &payload_json,
+                    &env_vars_string,
+                    deadline_unix_ms,
+                );
+                unsafe { start(&context) }
+            }
+        };
+        let (stderr, metrics) = match call_state {
+            Some(state) => state.finish(),
+            None => (String::new(), HashMap::new()),
+        };
+
+        if result.is_null() {
+            return Err("Library returned a null result".to_string());
+        }
+
+        let free_string = library.free_string;
+        defer! {
+            unsafe { free_string(result) }
+        };
+
+        let stdout = unsafe { CStr::from_ptr(result) }
+            .to_string_lossy()
+            .to_string();
+
+        Ok(Execution {
+            exit_code: None,
+            stdout,
+            stderr,
+            metrics,
+        })
+    }
+}
+
+/// Picks the signature of `start` from the module mode and what the library
+/// declared, rejecting ABI versions this host cannot drive.
+fn start_kind(
+    mode: ModuleMode,
+    abi_version: u32,
+    accepts_payload: bool,
+) -> Result<StartKind, String> {
+    if !SUPPORTED_ABI_VERSIONS.contains(&abi_version) {
+        return Err(format!(
+            "Unsupported ABI version {} (supported: {:?})",
+            abi_version, SUPPORTED_ABI_VERSIONS
+        ));
+    }
+
+    Ok(match mode {
+        _ if abi_version >= CONTEXT_ABI_VERSION => StartKind::Context,
+        ModuleMode::Worker => StartKind::Worker,
+        ModuleMode::Runner if accepts_payload => StartKind::PayloadRunner,
+        ModuleMode::Runner => StartKind::Runner,
+    })
+}
+
+fn missing_symbol(symbol: &str, mode: ModuleMode, abi_version: u32) -> String {
+    let expected = match mode {
+        _ if abi_version >= CONTEXT_ABI_VERSION => {
+            "start(const hc_context* ctx) and free_string(char*)"
+        }
+        ModuleMode::Worker => "start() and free_string(char*)",
+        ModuleMode::Runner => "start(char* env) and free_string(char*)",
+    };
+    format!(
+        "Missing symbol `{}`, a {:?} library must export {}",
+        symbol, mode, expected
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn start_kind_follows_mode_and_payload_marker() {
+        assert_eq!(
+            start_kind(ModuleMode::Worker, 1, true),
+            Ok(StartKind::Worker)
+        );
+        assert_eq!(
+            start_kind(ModuleMode::Runner, 1, false),
+            Ok(StartKind::Runner)
+        );
+        assert_eq!(
+            start_kind(ModuleMode::Runner, 1, true),
+            Ok(StartKind::PayloadRunner)
+        );
+    }
+
+    #[test]
+    fn context_abi_ignores_mode_and_payload_marker() {
+        for mode in [ModuleMode::Worker, ModuleMode::Runner] {
+            assert_eq!(
+                start_kind(mode, CONTEXT_ABI_VERSION, false),
+                Ok(StartKind::Context)
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_abi_version_is_rejected() {
+        let err = start_kind(ModuleMode::Worker, CONTEXT_ABI_VERSION + 1, false).unwrap_err();
+        assert!(err.contains(&format!(
+            "Unsupported ABI version {}",
+            CONTEXT_ABI_VERSION + 1
+        )));
+    }
+
+    #[test]
+    fn missing_symbol_names_expected_exports() {
+        assert_eq!(
+            missing_symbol("start", ModuleMode::Runner, 1),
+            "Missing symbol `start`, a Runner library must export start(char* env) and free_string(char*)"
+        );
+        assert!(
+            missing_symbol("free_string", ModuleMode::Worker, CONTEXT_ABI_VERSION)
+                .ends_with("start(const hc_context* ctx) and free_string(char*)")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn library_without_exports_fails_to_load() {
+        // The C library declares no ABI version, so it is driven with
+        // version 1 and lacks `start`.
+        let mut module = NativeModule::new(
+            "libc".to_string(),
+            "libc.so.6".to_string(),
+            ModuleMode::Worker,
+            String::new(),
+            Host::new(Arc::new(Mutex::new(sqlite::open(":memory:").unwrap()))),
+            ModuleConfig::default(),
+        );
+
+        assert_eq!(
+            module.load().unwrap_err(),
+            missing_symbol("start", ModuleMode::Worker, 1)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use wasmer::{FunctionEnv, Module, Store};
+use wasmer_wasix::{types::Signal, Pipe, WasiEnv, WasiEnvBuilder, WasiProcess, WasiRuntimeError};
+
+use crate::{config::ModuleConfig, types::RunPayload};
+
+use super::{
+    host::Host,
+    wasm_host::{self, GuestEnv},
+    CheckModule, Execution, Interrupt,
+};
+
+pub struct WasmModule {
+    module_name: String,
+    bytes: Vec<u8>,
+    host: Host,
+    module_config: ModuleConfig,
+    store: Store,
+    module: Option<Module>,
+    /// WASIX drives its tasks on the current Tokio runtime, which module
+    /// threads do not have, so every instance brings its own.
+    runtime: Option<tokio::runtime::Runtime>,
+    running: Arc<Mutex<RunningProcess>>,
+}
+
+/// The WASIX process of the execution in progress, shared with the interrupt
+/// handle so a cancelled execution can be killed.
+#[derive(Default)]
+struct RunningProcess {
+    process: Option<WasiProcess>,
+    killed: bool,
+}
+
+impl RunningProcess {
+    fn start(&mut self, process: WasiProcess) {
+        if self.killed {
+            process.signal_process(Signal::Sigkill);
+        }
+        self.process = Some(process);
+    }
+
+    /// The guest exits at its next system call, including any sleep or read
+    /// it is blocked in. Pure computation is not interrupted.
+    fn kill(&mut self) {
+        self.killed = true;
+        if let Some(process) = &self.process {
+            process.signal_process(Signal::Sigkill);
+        }
+    }
+}
+
+impl WasmModule {
+    pub fn new(
+        module_name: String,
+        bytes: Vec<u8>,
+        host: Host,
+        module_config: ModuleConfig,
+    ) -> Self {
+        WasmModule {
+            module_name,
+            bytes,
+            host,
+            module_config,
+            store: Store::default(),
+            module: None,
+            runtime: None,
+            running: Arc::default(),
+        }
+    }
+
+    /// Instantiates the module with the WASI and `health_check` imports and
+    /// runs its entry point, returning the exit code.
+    fn run(
+        &mut self,
+        builder: WasiEnvBuilder,
+        module: Module,
+        guest_env: &FunctionEnv<GuestEnv>,
+    ) -> Result<i32, String> {
+        let Some(runtime) = &self.runtime else {
+            return Err("Wasm module is not loaded".to_string());
+        };
+        let _guard = runtime.enter();
+
+        let imports = wasm_host::imports(&mut self.store, guest_env);
+        let (instance, wasi_env) = builder
+            .imports(&imports)
+            .instantiate(module, &mut self.store)
+            .map_err(|err| format!("Could not instantiate Wasm module: {}", err))?;
+
+        if let Ok(memory) = instance.exports.get_memory("memory") {
+            guest_env.as_mut(&mut self.store).set_memory(memory.clone());
+        }
+
+        unsafe { wasi_env.bootstrap(&mut self.store) }
+            .map_err(|err| format!("Could not bootstrap Wasm module: {}", err))?;
+        let start = instance
+            .exports
+            .get_function("_start")
+            .map_err(|err| format!("Could not find entry point: {}", err))?;
+        wasi_env.data(&self.store).thread.set_status_running();
+        let process = wasi_env.data(&self.store).process.clone();
+        self.running.lock().unwrap().start(process);
+
+        let result = start.call(&mut self.store, &[]);
+
+        // A non-zero exit is reported by WASIX as an error carrying the code,
+        // anything else is a genuine failure to run the module.
+        let exit_code = match result {
+            Ok(_) => 0,
+            Err(err) => {
+                let err = WasiRuntimeError::from(err);
+                match err.as_exit_code() {
+                    Some(code) => code.raw(),
+                    None => return Err(format!("Could not run Wasm module: {}", err)),
+                }
+            }
+        };
+
+        // Closes the module's files, including the stdout and stderr pipes.
+        wasi_env.on_exit(&mut self.store, Some(exit_code.into()));
+        Ok(exit_code)
+    }
+}
+
+impl CheckModule for WasmModule {
+    fn name(&self) -> &str {
+        &self.module_name
+    }
+
+    fn fresh_instance(&self) -> Box<dyn CheckModule> {
+        Box::new(WasmModule::new(
+            self.module_name.clone(),
+            self.bytes.clone(),
+            self.host.clone(),
+            self.module_config.clone(),
+        ))
+    }
+
+    fn load(&mut self) -> Result<(), String> {
+        let module = Module::new(&self.store, &self.bytes)
+            .map_err(|err| format!("Could not compile Wasm module: {}", err))?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|err| format!("Could not create runtime: {}", err))?;
+        self.module = Some(module);
+        self.runtime = Some(runtime);
+        Ok(())
+    }
+
+    fn execute(&mut self, payload: Option<&RunPayload>) -> Result<Execution, String> {
+        let module = match &self.module {
+            Some(val) => val.clone(),
+            None => return Err("Wasm module is not loaded".to_string()),
+        };
+
+        let (stdout_tx, mut stdout_rx) = Pipe::channel();
+        let (stderr_tx, mut stderr_rx) = Pipe::channel();
+
+        let mut builder = WasiEnv::builder(&self.module_name)
+            .env("HEALTH_CHECK_CONFIG", self.module_config.settings_json())
+            .stdout(Box::new(stdout_tx))
+            .stderr(Box::new(stderr_tx));
+
+        // Stdin is always a closed pipe, holding the payload JSON if there is
+        // one, so the module never reads the host's own stdin.
+        let (mut stdin_tx, stdin_rx) = Pipe::channel();
+        if let Some(payload) = payload {
+            let payload_json = serde_json::to_string(payload)
+                .map_err(|err| format!("Could not serialize payload: {}", err))?;
+            stdin_tx
+                .write_all(payload_json.as_bytes())
+                .map_err(|err| format!("Could not write stdin: {}", err))?;
+
+            builder = builder
+                .args(&payload.args)
+                .envs(&payload.env)
+                .env("HEALTH_CHECK_PAYLOAD", "1");
+        }
+        stdin_tx.close();
+        builder = builder.stdin(Box::new(stdin_rx));
+
+        let guest_env = FunctionEnv::new(
+            &mut self.store,
+            GuestEnv::new(self.host.clone(), self.module_config.clone()),
+        );
+        let exit_code = self.run(builder, module, &guest_env)?;
+        let (log, metrics) = guest_env.as_mut(&mut self.store).finish();
+
+        let mut stdout = String::new();
+        stdout_rx
+            .read_to_string(&mut stdout)
+            .map_err(|err| format!("Could not read stdout: {}", err))?;
+
+        let mut stderr = String::new();
+        stderr_rx
+            .read_to_string(&mut stderr)
+            .map_err(|err| format!("Could not read stderr: {}", err))?;
+        stderr.push_str(&log);
+
+        Ok(Execution {
+            exit_code: Some(exit_code),
+            stdout,
+            stderr,
+            metrics,
+        })
+    }
+
+    fn interrupt(&self) -> Option<Interrupt> {
+        let running = self.running.clone();
+        Some(Box::new(move || running.lock().unwrap().kill()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// Sleeps for a minute in `poll_oneoff`.
+    const SLEEPER: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "poll_oneoff"
+            (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "_start")
+            ;; A relative timeout on the monotonic clock.
+            (i32.store (i32.const 16) (i32.const 1))
+            (i64.store (i32.const 24) (i64.const 60000000000))
+            (drop (call $poll_oneoff
+              (i32.const 0) (i32.const 64) (i32.const 1) (i32.const 128)))))
+    "#;
+
+    #[test]
+    fn interrupt_kills_blocked_execution() {
+        let mut module = WasmModule::new(
+            "sleeper".to_string(),
+            SLEEPER.as_bytes().to_vec(),
+            Host::new(Arc::new(Mutex::new(sqlite::open(":memory:").unwrap()))),
+            ModuleConfig::default(),
+        );
+        module.load().unwrap();
+
+        let interrupt = module.interrupt().unwrap();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            interrupt();
+        });
+
+        let started = Instant::now();
+        let execution = module.execute(None).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert_ne!(execution.exit_code, Some(0));
+    }
+}
@@ -0,0 +1,426 @@
+//! Functions imported by Wasm modules from the `health_check` namespace.
+//!
+//! Strings are passed as a pointer and length into the module's memory.
+//! `kv_get` writes the value into a buffer provided by the module and returns
+//! its length, or -1 if the key has no value; when the value is larger than
+//! the buffer nothing is written and the module may retry with a buffer of
+//! the returned length.
+//!
+//! `http_request` takes an [`HttpRequest`] as JSON, sends it and returns the
+//! length of the [`HttpResponse`] JSON, which the module then copies out with
+//! `http_response` following the same buffer rules as `kv_get`.
+
+use std::collections::HashMap;
+
+use wasmer::{imports, Function, FunctionEnv, FunctionEnvMut, Imports, Memory, Store};
+
+use crate::config::ModuleConfig;
+
+use super::{
+    host::Host,
+    http::{self, HttpRequest},
+};
+
+/// Longest string a module may pass to a host function.
+const MAX_STRING_LEN: u32 = 1024 * 1024;
+
+/// Log bytes kept per execution, later lines are dropped.
+const MAX_LOG_LEN: usize = 64 * 1024;
+
+/// Per-execution state of the imports.
+pub struct GuestEnv {
+    host: Host,
+    module_config: ModuleConfig,
+    memory: Option<Memory>,
+    log: String,
+    log_truncated: bool,
+    metrics: HashMap<String, f64>,
+    /// JSON of the last HTTP response, until the module copies it out.
+    http_response: Vec<u8>,
+}
+
+impl GuestEnv {
+    pub fn new(host: Host, module_config: ModuleConfig) -> Self {
+        GuestEnv {
+            host,
+            module_config,
+            memory: None,
+            log: String::new(),
+            log_truncated: false,
+            metrics: HashMap::new(),
+            http_response: Vec::new(),
+        }
+    }
+
+    /// Has to be called once the instance exists, before its entry point.
+    pub fn set_memory(&mut self, memory: Memory) {
+        self.memory = Some(memory);
+    }
+
+    /// The log lines and metrics reported during the execution.
+    pub fn finish(&mut self) -> (String, HashMap<String, f64>) {
+        self.log_truncated = false;
+        (
+            std::mem::take(&mut self.log),
+            std::mem::take(&mut self.metrics),
+        )
+    }
+}
+
+pub fn imports(store: &mut Store, env: &FunctionEnv<GuestEnv>) -> Imports {
+    imports! {
+        "health_check" => {
+            "kv_get" => Function::new_typed_with_env(store, env, kv_get),
+            "kv_set" => Function::new_typed_with_env(store, env, kv_set),
+            "log" => Function::new_typed_with_env(store, env, log),
+            "emit_metric" => Function::new_typed_with_env(store, env, emit_metric),
+            "now" => Function::new_typed(store, now),
+            "http_request" => Function::new_typed_with_env(store, env, http_request),
+            "http_response" => Function::new_typed_with_env(store, env, http_response),
+        }
+    }
+}
+
+/// Copies a string out of the module's memory. Ranges outside of the memory
+/// or longer than [`MAX_STRING_LEN`] are rejected before allocating.
+fn read_string(env: &FunctionEnvMut<GuestEnv>, ptr: u32, len: u32) -> Option<String> {
+    if len > MAX_STRING_LEN {
+        return None;
+    }
+    let view = env.data().memory.as_ref()?.view(env);
+    if ptr as u64 + len as u64 > view.data_size() {
+        return None;
+    }
+    let mut buffer = vec![0; len as usize];
+    view.read(ptr as u64, &mut buffer).ok()?;
+    String::from_utf8(buffer).ok()
+}
+
+fn kv_get(
+    env: FunctionEnvMut<GuestEnv>,
+    key_ptr: u32,
+    key_len: u32,
+    out_ptr: u32,
+    out_cap: u32,
+) -> i32 {
+    let Some(key) = read_string(&env, key_ptr, key_len) else {
+        return -1;
+    };
+    let Some(value) = env.data().host.kv_get(&key) else {
+        return -1;
+    };
+
+    write_buffer(&env, value.as_bytes(), out_ptr, out_cap)
+}
+
+/// Copies `bytes` into the module's buffer if they fit and returns their
+/// length either way, or -1 if the memory could not be written.
+fn write_buffer(env: &FunctionEnvMut<GuestEnv>, bytes: &[u8], out_ptr: u32, out_cap: u32) -> i32 {
+    if bytes.len() <= out_cap as usize {
+        let Some(memory) = env.data().memory.as_ref() else {
+            return -1;
+        };
+        if memory.view(env).write(out_ptr as u64, bytes).is_err() {
+            return -1;
+        }
+    }
+    bytes.len() as i32
+}
+
+fn kv_set(
+    env: FunctionEnvMut<GuestEnv>,
+    key_ptr: u32,
+    key_len: u32,
+    value_ptr: u32,
+    value_len: u32,
+) -> i32 {
+    let (Some(key), Some(value)) = (
+        read_string(&env, key_ptr, key_len),
+        read_string(&env, value_ptr, value_len),
+    ) else {
+        return -1;
+    };
+
+    if env.data().host.kv_set(&key, &value) {
+        0
+    } else {
+        -1
+    }
+}
+
+fn log(mut env: FunctionEnvMut<GuestEnv>, level: i32, ptr: u32, len: u32) {
+    let Some(message) = read_string(&env, ptr, len) else {
+        return;
+    };
+    let level = match level {
+        0 => "DEBUG",
+        1 => "INFO",
+        2 => "WARN",
+        _ => "ERROR",
+    };
+
+    let data = env.data_mut();
+    for line in message.lines() {
+        if data.log.len() >= MAX_LOG_LEN {
+            if !data.log_truncated {
+                data.log.push_str("[WARN] Log truncated by the host\n");
+                data.log_truncated = true;
+            }
+            break;
+        }
+        data.log.push_str(&format!("[{}] {}\n", level, line));
+    }
+}
+
+fn emit_metric(mut env: FunctionEnvMut<GuestEnv>, name_ptr: u32, name_len: u32, value: f64) {
+    if let Some(name) = read_string(&env, name_ptr, name_len) {
+        env.data_mut().metrics.insert(name, value);
+    }
+}
+
+fn http_request(mut env: FunctionEnvMut<GuestEnv>, request_ptr: u32, request_len: u32) -> i32 {
+    let Some(request) = read_string(&env, request_ptr, request_len) else {
+        return -1;
+    };
+    let request = match serde_json::from_str::<HttpRequest>(&request) {
+        Ok(val) => val,
+        Err(_) => return -1,
+    };
+
+    let response = http::send(&request, &env.data().module_config);
+    let data = env.data_mut();
+    data.http_response = serde_json::to_vec(&response).unwrap_or_default();
+    data.http_response.len() as i32
+}
+
+fn http_response(env: FunctionEnvMut<GuestEnv>, out_ptr: u32, out_cap: u32) -> i32 {
+    write_buffer(&env, &env.data().http_response, out_ptr, out_cap)
+}
+
+/// Milliseconds since the Unix epoch.
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use wasmer::{Instance, Module, Value};
+
+    use super::*;
+
+    /// Re-exports the imports so they can be called with arbitrary pointers.
+    const GUEST: &str = r#"
+        (module
+          (import "health_check" "kv_get" (func $kv_get (param i32 i32 i32 i32) (result i32)))
+          (import "health_check" "kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))
+          (import "health_check" "log" (func $log (param i32 i32 i32)))
+          (import "health_check" "emit_metric" (func $emit_metric (param i32 i32 f64)))
+          (import "health_check" "now" (func $now (result i64)))
+          (import "health_check" "http_request" (func $http_request (param i32 i32) (result i32)))
+          (import "health_check" "http_response" (func $http_response (param i32 i32) (result i32)))
+          (memory (export "memory") 32)
+          (data (i32.const 0) "city")
+          (data (i32.const 16) "Berlin")
+          (data (i32.const 32) "first\nsecond")
+          (data (i32.const 48) "bad \ff byte")
+          (data (i32.const 64) "{\"url\":\"http://example.com/\"}")
+          (export "kv_get" (func $kv_get))
+          (export "kv_set" (func $kv_set))
+          (export "log" (func $log))
+          (export "emit_metric" (func $emit_metric))
+          (export "now" (func $now))
+          (export "http_request" (func $http_request))
+          (export "http_response" (func $http_response)))
+    "#;
+
+    /// Size of the guest's memory, 32 pages.
+    const MEMORY_SIZE: i32 = 32 * 65536;
+
+    struct Guest {
+        store: Store,
+        instance: Instance,
+        env: FunctionEnv<GuestEnv>,
+    }
+
+    impl Guest {
+        fn new() -> Self {
+            let mut store = Store::default();
+            let connection = sqlite::open(":memory:").unwrap();
+            let env = FunctionEnv::new(
+                &mut store,
+                GuestEnv::new(
+                    Host::new(Arc::new(Mutex::new(connection))),
+                    ModuleConfig::default(),
+                ),
+            );
+            let module = Module::new(&store, GUEST).unwrap();
+            let imports = imports(&mut store, &env);
+            let instance = Instance::new(&mut store, &module, &imports).unwrap();
+            let memory = instance.exports.get_memory("memory").unwrap().clone();
+            env.as_mut(&mut store).set_memory(memory);
+
+            Guest {
+                store,
+                instance,
+                env,
+            }
+        }
+
+        fn call(&mut self, name: &str, args: &[Value]) -> Option<Value> {
+            let function = self.instance.exports.get_function(name).unwrap();
+            function
+                .call(&mut self.store, args)
+                .unwrap()
+                .first()
+                .cloned()
+        }
+
+        fn call_i32(&mut self, name: &str, args: &[i32]) -> i32 {
+            let args: Vec<Value> = args.iter().map(|arg| Value::I32(*arg)).collect();
+            self.call(name, &args).unwrap().unwrap_i32()
+        }
+
+        fn read(&self, offset: u64, len: usize) -> Vec<u8> {
+            let memory = self.instance.exports.get_memory("memory").unwrap();
+            let mut buffer = vec![0; len];
+            memory.view(&self.store).read(offset, &mut buffer).unwrap();
+            buffer
+        }
+
+        fn finish(&mut self) -> (String, HashMap<String, f64>) {
+            self.env.as_mut(&mut self.store).finish()
+        }
+    }
+
+    #[test]
+    fn kv_get_writes_value_set_before() {
+        let mut guest = Guest::new();
+
+        assert_eq!(guest.call_i32("kv_set", &[0, 4, 16, 6]), 0);
+        assert_eq!(guest.call_i32("kv_get", &[0, 4, 100, 64]), 6);
+        assert_eq!(guest.read(100, 6), b"Berlin");
+    }
+
+    #[test]
+    fn kv_get_returns_required_length_for_small_buffer() {
+        let mut guest = Guest::new();
+        guest.call_i32("kv_set", &[0, 4, 16, 6]);
+
+        assert_eq!(guest.call_i32("kv_get", &[0, 4, 100, 3]), 6);
+        assert_eq!(guest.read(100, 6), [0; 6]);
+    }
+
+    #[test]
+    fn kv_get_of_missing_key_is_negative() {
+        let mut guest = Guest::new();
+
+        assert_eq!(guest.call_i32("kv_get", &[0, 4, 100, 64]), -1);
+    }
+
+    #[test]
+    fn out_of_bounds_pointers_are_rejected() {
+        let mut guest = Guest::new();
+
+        assert_eq!(guest.call_i32("kv_set", &[0, 4, MEMORY_SIZE - 2, 6]), -1);
+        assert_eq!(guest.call_i32("kv_set", &[-1, 4, 16, 6]), -1);
+        assert_eq!(guest.call_i32("kv_get", &[0, 4, 100, 64]), -1);
+
+        guest.call_i32("kv_set", &[0, 4, 16, 6]);
+        assert_eq!(guest.call_i32("kv_get", &[MEMORY_SIZE - 2, 4, 100, 64]), -1);
+        assert_eq!(guest.call_i32("kv_get", &[0, 4, MEMORY_SIZE - 2, 64]), -1);
+    }
+
+    #[test]
+    fn strings_longer_than_the_limit_are_rejected() {
+        let mut guest = Guest::new();
+        // The second page onwards holds only zero bytes, a valid string.
+        let (ptr, len) = (65536, MAX_STRING_LEN as i32);
+
+        guest.call(
+            "emit_metric",
+            &[Value::I32(ptr), Value::I32(len), Value::F64(1.0)],
+        );
+        guest.call(
+            "emit_metric",
+            &[Value::I32(ptr), Value::I32(len + 1), Value::F64(2.0)],
+        );
+
+        let (_, metrics) = guest.finish();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics.values().next(), Some(&1.0));
+    }
+
+    #[test]
+    fn emitted_metrics_are_collected() {
+        let mut guest = Guest::new();
+
+        guest.call(
+            "emit_metric",
+            &[Value::I32(16), Value::I32(6), Value::F64(3.5)],
+        );
+
+        let (_, metrics) = guest.finish();
+        assert_eq!(metrics, HashMap::from([("Berlin".to_string(), 3.5)]));
+    }
+
+    #[test]
+    fn log_prefixes_lines_and_drops_invalid_utf8() {
+        let mut guest = Guest::new();
+
+        guest.call("log", &[Value::I32(1), Value::I32(32), Value::I32(12)]);
+        guest.call("log", &[Value::I32(2), Value::I32(48), Value::I32(10)]);
+
+        let (log, _) = guest.finish();
+        assert_eq!(log, "[INFO] first\n[INFO] second\n");
+    }
+
+    #[test]
+    fn log_is_truncated_once_past_the_limit() {
+        let mut guest = Guest::new();
+
+        for _ in 0..MAX_LOG_LEN / 10 {
+            guest.call("log", &[Value::I32(1), Value::I32(32), Value::I32(12)]);
+        }
+
+        let (log, _) = guest.finish();
+        assert!(log.len() < MAX_LOG_LEN + 64);
+        assert!(log.ends_with("\n[WARN] Log truncated by the host\n"));
+        assert_eq!(log.matches("truncated").count(), 1);
+    }
+
+    #[test]
+    fn http_response_follows_the_buffer_protocol() {
+        let mut guest = Guest::new();
+
+        let len = guest.call_i32("http_request", &[64, 29]);
+        assert!(len > 0);
+        assert_eq!(guest.call_i32("http_response", &[1000, 4]), len);
+        assert_eq!(guest.read(1000, 4), [0; 4]);
+        assert_eq!(guest.call_i32("http_response", &[1000, 1024]), len);
+
+        let response: serde_json::Value =
+            serde_json::from_slice(&guest.read(1000, len as usize)).unwrap();
+        assert_eq!(response["error"], "Host example.com is not allowed");
+    }
+
+    #[test]
+    fn http_request_rejects_invalid_json() {
+        let mut guest = Guest::new();
+
+        assert_eq!(guest.call_i32("http_request", &[16, 6]), -1);
+    }
+
+    #[test]
+    fn now_is_unix_millis() {
+        let mut guest = Guest::new();
+        let before = now();
+
+        let value = guest.call("now", &[]).unwrap().unwrap_i64();
+        assert!(value >= before && value - before < 1000);
+    }
+}
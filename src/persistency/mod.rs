@@ -1,9 +1,14 @@
 use std::error::Error;
 
+use serde::Serialize;
+
+use crate::protocol::ResultStatus;
+
 pub trait Save {
     fn persist(&self, conn: &sqlite::Connection) -> Result<(), Box<dyn Error>>;
 }
 
+#[derive(Clone, Debug, Serialize)]
 pub struct KeyValuePair {
     pub key: String,
     pub value: String,
@@ -36,3 +41,383 @@ impl Save for KeyValuePair {
         Ok(())
     }
 }
+
+impl KeyValuePair {
+    /// Reads the value stored for `key`, if any.
+    pub fn load(conn: &sqlite::Connection, key: &str) -> Result<Option<String>, Box<dyn Error>> {
+        conn.execute(
+            "
+            CREATE TABLE IF NOT EXISTS key_value_pairs (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+        ",
+        )?;
+
+        let mut statement = conn.prepare("SELECT value FROM key_value_pairs WHERE key = ?;")?;
+        statement.bind((1, key))?;
+
+        if let sqlite::State::Row = statement.next()? {
+            return Ok(Some(statement.read::<String, _>(0)?));
+        }
+
+        Ok(None)
+    }
+}
+
+/// One execution of a runner, kept as its run history.
+pub struct RunRecord {
+    pub module_name: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub success: bool,
+    pub payload: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Save for RunRecord {
+    fn persist(&self, conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+        conn.execute(
+            "
+            CREATE TABLE IF NOT EXISTS run_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                module_name TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL,
+                success INTEGER NOT NULL,
+                payload TEXT,
+                error TEXT
+            );
+        ",
+        )?;
+
+        let insert_query = "
+            INSERT INTO run_history (module_name, started_at, finished_at, success, payload, error)
+            VALUES (?, ?, ?, ?, ?, ?);
+        ";
+
+        let mut statement = conn.prepare(insert_query)?;
+        statement.bind((1, self.module_name.as_str()))?;
+        statement.bind((2, self.started_at as i64))?;
+        statement.bind((3, self.finished_at as i64))?;
+        statement.bind((4, self.success as i64))?;
+        statement.bind((5, self.payload.as_deref()))?;
+        statement.bind((6, self.error.as_deref()))?;
+        statement.next()?;
+
+        Ok(())
+    }
+}
+
+/// Status of one worker execution, kept as the history uptime is computed
+/// from.
+pub struct StatusRecord {
+    pub module_name: String,
+    pub recorded_at: u64,
+    pub status: ResultStatus,
+}
+
+/// Records older than this are deleted when a new one is written, so it is
+/// also the longest uptime window.
+pub const STATUS_HISTORY_RETENTION_SECS: u64 = 31 * 24 * 60 * 60;
+
+fn create_status_history(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "
+            CREATE TABLE IF NOT EXISTS status_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                module_name TEXT NOT NULL,
+                recorded_at INTEGER NOT NULL,
+                status TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS status_history_module
+                ON status_history (module_name, recorded_at);
+            CREATE INDEX IF NOT EXISTS status_history_recorded_at
+                ON status_history (recorded_at);
+        ",
+    )?;
+    Ok(())
+}
+
+impl Save for StatusRecord {
+    fn persist(&self, conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+        create_status_history(conn)?;
+
+        let mut statement = conn.prepare(
+            "INSERT INTO status_history (module_name, recorded_at, status) VALUES (?, ?, ?);",
+        )?;
+        statement.bind((1, self.module_name.as_str()))?;
+        statement.bind((2, self.recorded_at as i64))?;
+        statement.bind((3, self.status.as_str()))?;
+        statement.next()?;
+
+        let mut statement = conn.prepare("DELETE FROM status_history WHERE recorded_at < ?;")?;
+        statement.bind((
+            1,
+            self.recorded_at
+                .saturating_sub(STATUS_HISTORY_RETENTION_SECS) as i64,
+        ))?;
+        statement.next()?;
+
+        Ok(())
+    }
+}
+
+impl StatusRecord {
+    /// Number of records per status for a module since `since`.
+    pub fn count_since(
+        conn: &sqlite::Connection,
+        module_name: &str,
+        since: u64,
+    ) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+        create_status_history(conn)?;
+
+        let mut statement = conn.prepare(
+            "
+            SELECT status, COUNT(*) FROM status_history
+            WHERE module_name = ? AND recorded_at >= ?
+            GROUP BY status;
+        ",
+        )?;
+        statement.bind((1, module_name))?;
+        statement.bind((2, since as i64))?;
+
+        let mut counts = Vec::new();
+        while let sqlite::State::Row = statement.next()? {
+            counts.push((
+                statement.read::<String, _>(0)?,
+                statement.read::<i64, _>(1)? as u64,
+            ));
+        }
+
+        Ok(counts)
+    }
+}
+
+/// A period a module was down, opened when it stops being up and closed once
+/// it recovers.
+#[derive(Clone, Debug, Serialize)]
+pub struct Incident {
+    pub id: i64,
+    pub module_name: String,
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    pub duration_secs: Option<u64>,
+    /// Message of the execution that opened the incident.
+    pub first_message: Option<String>,
+    pub acknowledged: bool,
+    pub acknowledged_at: Option<u64>,
+    pub acknowledged_by: Option<String>,
+}
+
+fn create_incidents(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        "
+            CREATE TABLE IF NOT EXISTS incidents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                module_name TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                ended_at INTEGER,
+                duration_secs INTEGER,
+                first_message TEXT,
+                acknowledged INTEGER NOT NULL DEFAULT 0,
+                acknowledged_at INTEGER,
+                acknowledged_by TEXT
+            );
+        ",
+    )?;
+    Ok(())
+}
+
+const INCIDENT_COLUMNS: &str = "id, module_name, started_at, ended_at, duration_secs, \
+    first_message, acknowledged, acknowledged_at, acknowledged_by";
+
+impl Incident {
+    /// Opens an incident for the module unless one is open already. Returns
+    /// whether a new one was opened.
+    pub fn open(
+        conn: &sqlite::Connection,
+        module_name: &str,
+        started_at: u64,
+        first_message: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        create_incidents(conn)?;
+
+        let mut statement = conn.prepare(
+            "
+            INSERT INTO incidents (module_name, started_at, first_message)
+            SELECT ?, ?, ?
+            WHERE NOT EXISTS (
+                SELECT 1 FROM incidents WHERE module_name = ? AND ended_at IS NULL
+            );
+        ",
+        )?;
+        statement.bind((1, module_name))?;
+        statement.bind((2, started_at as i64))?;
+        statement.bind((3, first_message))?;
+        statement.bind((4, module_name))?;
+        statement.next()?;
+
+        Ok(conn.change_count() > 0)
+    }
+
+    /// Closes the open incident of the module, if any. Returns whether one
+    /// was closed.
+    pub fn close(
+        conn: &sqlite::Connection,
+        module_name: &str,
+        ended_at: u64,
+    ) -> Result<bool, Box<dyn Error>> {
+        create_incidents(conn)?;
+
+        let mut statement = conn.prepare(
+            "
+            UPDATE incidents SET ended_at = ?, duration_secs = ? - started_at
+            WHERE module_name = ? AND ended_at IS NULL;
+        ",
+        )?;
+        statement.bind((1, ended_at as i64))?;
+        statement.bind((2, ended_at as i64))?;
+        statement.bind((3, module_name))?;
+        statement.next()?;
+
+        Ok(conn.change_count() > 0)
+    }
+
+    /// Marks an incident as acknowledged, keeping the first acknowledgement.
+    /// Returns the incident, or None if it does not exist.
+    pub fn acknowledge(
+        conn: &sqlite::Connection,
+        id: i64,
+        acknowledged_at: u64,
+        acknowledged_by: Option<&str>,
+    ) -> Result<Option<Incident>, Box<dyn Error>> {
+        create_incidents(conn)?;
+
+        let mut statement = conn.prepare(
+            "
+            UPDATE incidents SET acknowledged = 1, acknowledged_at = ?, acknowledged_by = ?
+            WHERE id = ? AND acknowledged = 0;
+        ",
+        )?;
+        statement.bind((1, acknowledged_at as i64))?;
+        statement.bind((2, acknowledged_by))?;
+        statement.bind((3, id))?;
+        statement.next()?;
+
+        let mut statement = conn.prepare(format!(
+            "SELECT {} FROM incidents WHERE id = ?;",
+            INCIDENT_COLUMNS
+        ))?;
+        statement.bind((1, id))?;
+
+        if let sqlite::State::Row = statement.next()? {
+            return Ok(Some(Incident::read(&statement)?));
+        }
+
+        Ok(None)
+    }
+
+    /// The most recent incidents first, optionally only those of one module
+    /// or only those still open.
+    pub fn list(
+        conn: &sqlite::Connection,
+        module_name: Option<&str>,
+        open_only: bool,
+        limit: usize,
+    ) -> Result<Vec<Incident>, Box<dyn Error>> {
+        create_incidents(conn)?;
+
+        let mut statement = conn.prepare(format!(
+            "
+            SELECT {} FROM incidents
+            WHERE (?1 IS NULL OR module_name = ?1) AND (?2 = 0 OR ended_at IS NULL)
+            ORDER BY started_at DESC, id DESC
+            LIMIT ?3;
+        ",
+            INCIDENT_COLUMNS
+        ))?;
+        statement.bind((1, module_name))?;
+        statement.bind((2, open_only as i64))?;
+        statement.bind((3, limit as i64))?;
+
+        let mut incidents = Vec::new();
+        while let sqlite::State::Row = statement.next()? {
+            incidents.push(Incident::read(&statement)?);
+        }
+
+        Ok(incidents)
+    }
+
+    fn read(statement: &sqlite::Statement) -> Result<Incident, Box<dyn Error>> {
+        Ok(Incident {
+            id: statement.read::<i64, _>(0)?,
+            module_name: statement.read::<String, _>(1)?,
+            started_at: statement.read::<i64, _>(2)? as u64,
+            ended_at: statement.read::<Option<i64>, _>(3)?.map(|val| val as u64),
+            duration_secs: statement.read::<Option<i64>, _>(4)?.map(|val| val as u64),
+            first_message: statement.read::<Option<String>, _>(5)?,
+            acknowledged: statement.read::<i64, _>(6)? != 0,
+            acknowledged_at: statement.read::<Option<i64>, _>(7)?.map(|val| val as u64),
+            acknowledged_by: statement.read::<Option<String>, _>(8)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_one_incident_per_outage_and_closes_it() {
+        let conn = sqlite::open(":memory:").unwrap();
+
+        assert!(Incident::open(&conn, "web", 100, Some("refused")).unwrap());
+        // Further failures belong to the same outage.
+        assert!(!Incident::open(&conn, "web", 160, Some("timeout")).unwrap());
+        assert!(Incident::open(&conn, "db", 120, None).unwrap());
+
+        assert!(Incident::close(&conn, "web", 400).unwrap());
+        assert!(!Incident::close(&conn, "web", 460).unwrap());
+
+        let incidents = Incident::list(&conn, Some("web"), false, 10).unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].first_message.as_deref(), Some("refused"));
+        assert_eq!(incidents[0].ended_at, Some(400));
+        assert_eq!(incidents[0].duration_secs, Some(300));
+
+        let open = Incident::list(&conn, None, true, 10).unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].module_name, "db");
+
+        // The next failure after recovery is a new incident.
+        assert!(Incident::open(&conn, "web", 500, None).unwrap());
+        let incidents = Incident::list(&conn, Some("web"), false, 10).unwrap();
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(incidents[0].started_at, 500);
+    }
+
+    #[test]
+    fn acknowledge_keeps_the_first_acknowledgement() {
+        let conn = sqlite::open(":memory:").unwrap();
+        Incident::open(&conn, "web", 100, None).unwrap();
+        let id = Incident::list(&conn, None, false, 1).unwrap()[0].id;
+
+        let incident = Incident::acknowledge(&conn, id, 150, Some("alice"))
+            .unwrap()
+            .unwrap();
+        assert!(incident.acknowledged);
+        assert_eq!(incident.acknowledged_at, Some(150));
+        assert_eq!(incident.acknowledged_by.as_deref(), Some("alice"));
+
+        let incident = Incident::acknowledge(&conn, id, 200, Some("bob"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(incident.acknowledged_at, Some(150));
+        assert_eq!(incident.acknowledged_by.as_deref(), Some("alice"));
+
+        assert!(Incident::acknowledge(&conn, id + 1, 200, None)
+            .unwrap()
+            .is_none());
+    }
+}
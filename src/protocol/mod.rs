@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::persistency::KeyValuePair;
+
+/// Highest version of the JSON result format understood by the parser.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Health of a service as reported by a module or decided by the host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultStatus {
+    Ok,
+    /// Working, but degraded, e.g. slow answers.
+    Warning,
+    /// Not working. Modules written before warnings existed report `down`.
+    #[serde(alias = "down")]
+    Critical,
+    /// The check ran but could not tell, or has not run yet.
+    #[default]
+    Unknown,
+    /// The health check itself failed.
+    Crash,
+}
+
+impl ResultStatus {
+    pub const ALL: [ResultStatus; 5] = [
+        ResultStatus::Ok,
+        ResultStatus::Warning,
+        ResultStatus::Critical,
+        ResultStatus::Unknown,
+        ResultStatus::Crash,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultStatus::Ok => "ok",
+            ResultStatus::Warning => "warning",
+            ResultStatus::Critical => "critical",
+            ResultStatus::Unknown => "unknown",
+            ResultStatus::Crash => "crash",
+        }
+    }
+
+    /// Whether the service counts as up, which includes degraded services.
+    pub fn is_up(&self) -> bool {
+        matches!(self, ResultStatus::Ok | ResultStatus::Warning)
+    }
+}
+
+/// Everything a module reported during a single execution.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ModuleOutput {
+    pub status: Option<ResultStatus>,
+    pub message: Option<String>,
+    pub latency_ms: Option<f64>,
+    pub metrics: HashMap<String, f64>,
+    pub kv: Vec<KeyValuePair>,
+    pub tags: Vec<String>,
+}
+
+/// The versioned JSON document a module may print instead of the legacy lines.
+///
+/// ```json
+/// {"version":1,"status":"ok","message":"3/3 pages up","latency_ms":42.0,
+///  "metrics":{"pages":3},"kv":{"light":"1"},"tags":["http"]}
+/// ```
+#[derive(Deserialize)]
+struct JsonResult {
+    version: u32,
+    status: Option<ResultStatus>,
+    message: Option<String>,
+    latency_ms: Option<f64>,
+    #[serde(default)]
+    metrics: HashMap<String, f64>,
+    #[serde(default)]
+    kv: HashMap<String, String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Parses the output of any module.
+///
+/// The whole output is first tried as a JSON result document, then every line
+/// is inspected on its own so JSON lines, the legacy status lines
+/// (`true`/`True`/`Warning`/`False`/`Unknown`/`Crash`) and `KV:key###value`
+/// lines can be mixed.
+/// The first status found wins.
+pub fn parse_output(output: &str) -> ModuleOutput {
+    let mut module_output = ModuleOutput::default();
+
+    if let Some(json_result) = parse_json_result(output.trim()) {
+        apply_json_result(&mut module_output, json_result);
+        return module_output;
+    }
+
+    for line in output.lines() {
+        let line = line.trim();
+
+        if let Some(json_result) = parse_json_result(line) {
+            apply_json_result(&mut module_output, json_result);
+            continue;
+        }
+
+        if let Some(key_value_pair) = parse_kv_line(line) {
+            module_output.kv.push(key_value_pair);
+            continue;
+        }
+
+        if module_output.status.is_none() {
+            module_output.status = match line {
+                "true" | "True" => Some(ResultStatus::Ok),
+                "Warning" => Some(ResultStatus::Warning),
+                "false" | "False" => Some(ResultStatus::Critical),
+                "Unknown" => Some(ResultStatus::Unknown),
+                "Crash" => Some(ResultStatus::Crash),
+                _ => None,
+            };
+        }
+    }
+
+    module_output
+}
+
+fn parse_json_result(text: &str) -> Option<JsonResult> {
+    if !text.starts_with('{') {
+        return None;
+    }
+
+    serde_json::from_str(text).ok()
+}
+
+fn apply_json_result(module_output: &mut ModuleOutput, json_result: JsonResult) {
+    if json_result.version > PROTOCOL_VERSION {
+        module_output.status = Some(ResultStatus::Crash);
+        module_output.message = Some(format!(
+            "Unsupported result protocol version {} (max {})",
+            json_result.version, PROTOCOL_VERSION
+        ));
+        return;
+    }
+
+    if module_output.status.is_none() {
+        module_output.status = json_result.status;
+    }
+    if json_result.message.is_some() {
+        module_output.message = json_result.message;
+    }
+    if json_result.latency_ms.is_some() {
+        module_output.latency_ms = json_result.latency_ms;
+    }
+    module_output.metrics.extend(json_result.metrics);
+    module_output.tags.extend(json_result.tags);
+    module_output.kv.extend(
+        json_result
+            .kv
+            .into_iter()
+            .map(|(key, value)| KeyValuePair { key, value }),
+    );
+}
+
+fn parse_kv_line(line: &str) -> Option<KeyValuePair> {
+    let filtered_data = line.strip_prefix("KV:")?;
+    let parts: Vec<&str> = filtered_data.split("###").collect();
+
+    if parts.len() != 2 {
+        return None;
+    }
+
+    Some(KeyValuePair {
+        key: parts[0].to_string(),
+        value: parts[1].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_legacy_status_and_kv_lines() {
+        let output = parse_output("KV:light###1\nTrue\nFalse\nKV:broken\n");
+
+        assert_eq!(output.status, Some(ResultStatus::Ok));
+        assert_eq!(output.kv.len(), 1);
+        assert_eq!(output.kv[0].key, "light");
+        assert_eq!(output.kv[0].value, "1");
+
+        assert_eq!(parse_output("Warning").status, Some(ResultStatus::Warning));
+        assert_eq!(parse_output("false").status, Some(ResultStatus::Critical));
+        assert_eq!(parse_output("Crash").status, Some(ResultStatus::Crash));
+        assert_eq!(parse_output("no status here").status, None);
+    }
+
+    #[test]
+    fn parses_json_document() {
+        let output = parse_output(
+            r#"{
+                "version": 1,
+                "status": "warning",
+                "message": "slow",
+                "latency_ms": 42.5,
+                "metrics": {"pages": 3},
+                "kv": {"light": "1"},
+                "tags": ["http"]
+            }"#,
+        );
+
+        assert_eq!(output.status, Some(ResultStatus::Warning));
+        assert_eq!(output.message.as_deref(), Some("slow"));
+        assert_eq!(output.latency_ms, Some(42.5));
+        assert_eq!(output.metrics.get("pages"), Some(&3.0));
+        assert_eq!(output.kv.len(), 1);
+        assert_eq!(output.tags, vec!["http".to_string()]);
+    }
+
+    #[test]
+    fn mixes_json_lines_with_legacy_lines() {
+        let output = parse_output(
+            "starting\n{\"version\":1,\"status\":\"down\",\"message\":\"refused\"}\nKV:a###b\nTrue",
+        );
+
+        // The first status found wins, `down` is the old name of critical.
+        assert_eq!(output.status, Some(ResultStatus::Critical));
+        assert_eq!(output.message.as_deref(), Some("refused"));
+        assert_eq!(output.kv.len(), 1);
+    }
+
+    #[test]
+    fn newer_protocol_version_is_a_crash() {
+        let output = parse_output(r#"{"version":2,"status":"ok"}"#);
+
+        assert_eq!(output.status, Some(ResultStatus::Crash));
+        assert!(output.message.unwrap().contains("version 2"));
+    }
+}
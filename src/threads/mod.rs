@@ -1,9 +1,102 @@
-mod dll_runner;
-mod dll_worker;
-mod wasm_runner;
-mod wasm_worker;
-
-pub use dll_runner::spawn_dll_runner_threads;
-pub use dll_worker::spawn_dll_worker_threads;
-pub use wasm_runner::spawn_wasm_runner_threads;
-pub use wasm_worker::spawn_wasm_worker_threads;
+mod queue;
+mod runner;
+mod supervisor;
+mod worker;
+
+pub use queue::{EnqueueError, RunQueue};
+pub use runner::spawn_runner_threads;
+pub use worker::spawn_worker_threads;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sqlite::Connection;
+
+use crate::{
+    logs::{LogStream, ModuleLogs},
+    modules::Execution,
+    persistency::{Incident, KeyValuePair, Save, StatusRecord},
+    protocol::ResultStatus,
+};
+
+fn persist_key_value_pairs(key_value_pairs: &[KeyValuePair], connection: &Arc<Mutex<Connection>>) {
+    for key_value_pair in key_value_pairs {
+        let connection = match connection.lock() {
+            Ok(val) => val,
+            Err(_) => return,
+        };
+
+        if key_value_pair.persist(&connection).is_ok() {
+            println!(
+                "Persisted key: {} with value: {}",
+                &key_value_pair.key, &key_value_pair.value
+            );
+        }
+    }
+}
+
+fn record_status(connection: &Arc<Mutex<Connection>>, module_name: &str, status: ResultStatus) {
+    let status_record = StatusRecord {
+        module_name: module_name.to_string(),
+        recorded_at: unix_timestamp(),
+        status,
+    };
+
+    if let Ok(connection) = connection.lock() {
+        if let Err(err) = status_record.persist(&connection) {
+            eprintln!("Error: Could not record status of {}: {}", module_name, err);
+        }
+    }
+}
+
+/// Opens an incident when a module is critical or crashed and closes it once
+/// the module is up again. Unknown statuses leave incidents as they are.
+fn track_incident(
+    connection: &Arc<Mutex<Connection>>,
+    module_name: &str,
+    status: ResultStatus,
+    message: Option<&str>,
+) {
+    let Ok(connection) = connection.lock() else {
+        return;
+    };
+
+    let result = match status {
+        ResultStatus::Unknown => return,
+        ResultStatus::Ok | ResultStatus::Warning => {
+            Incident::close(&connection, module_name, unix_timestamp()).map(|closed| {
+                if closed {
+                    println!("Closed incident of {}", module_name);
+                }
+            })
+        }
+        ResultStatus::Critical | ResultStatus::Crash => {
+            Incident::open(&connection, module_name, unix_timestamp(), message).map(|opened| {
+                if opened {
+                    println!("Opened incident of {}", module_name);
+                }
+            })
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!(
+            "Error: Could not track incident of {}: {}",
+            module_name, err
+        );
+    }
+}
+
+fn log_execution(module_logs: &ModuleLogs, module_name: &str, execution: &Execution) {
+    module_logs.append(module_name, LogStream::Stdout, &execution.stdout);
+    module_logs.append(module_name, LogStream::Stderr, &execution.stderr);
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex},
+};
+
+use tokio::sync::oneshot;
+
+use crate::{
+    config::{QueuePolicy, RunnerConfig},
+    types::{RunOutcome, RunPayload, Trigger},
+};
+
+#[derive(Debug)]
+pub enum EnqueueError {
+    /// The bounded queue has no room left.
+    Full,
+    /// No runner thread is consuming the queue anymore.
+    Closed,
+}
+
+/// A trigger waiting for, or going through, execution.
+pub struct QueuedRun {
+    pub payload: Option<RunPayload>,
+    /// Every caller waiting for this run, more than one once coalesced.
+    pub replies: Vec<oneshot::Sender<RunOutcome>>,
+    generation: u64,
+}
+
+struct QueueInner {
+    pending: VecDeque<QueuedRun>,
+    in_flight: usize,
+    consumers: usize,
+    /// Bumped for every accepted trigger, used to detect cancelled runs.
+    generation: u64,
+}
+
+/// The pending triggers of one runner, shared by the API and its pool of
+/// runner threads.
+pub struct RunQueue {
+    config: RunnerConfig,
+    inner: Mutex<QueueInner>,
+    available: Condvar,
+}
+
+impl RunQueue {
+    pub fn new(config: RunnerConfig) -> Self {
+        RunQueue {
+            config,
+            inner: Mutex::new(QueueInner {
+                pending: VecDeque::new(),
+                in_flight: 0,
+                consumers: 0,
+                generation: 0,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    pub fn push(&self, trigger: Trigger) -> Result<(), EnqueueError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.consumers == 0 {
+            return Err(EnqueueError::Closed);
+        }
+
+        match self.config.queue_policy {
+            QueuePolicy::Fifo => {}
+            QueuePolicy::Coalesce => {
+                if let Some(queued) = inner
+                    .pending
+                    .iter_mut()
+                    .find(|queued| queued.payload == trigger.payload)
+                {
+                    queued.replies.extend(trigger.reply);
+                    return Ok(());
+                }
+            }
+            QueuePolicy::CancelInFlight => {
+                for cancelled in inner.pending.drain(..) {
+                    reply_all(cancelled.replies, &RunOutcome::cancelled());
+                }
+            }
+        }
+
+        if let Some(max_queue) = self.config.max_queue {
+            if inner.pending.len() >= max_queue {
+                return Err(EnqueueError::Full);
+            }
+        }
+
+        inner.generation += 1;
+        let generation = inner.generation;
+        inner.pending.push_back(QueuedRun {
+            payload: trigger.payload,
+            replies: trigger.reply.into_iter().collect(),
+            generation,
+        });
+        self.available.notify_one();
+
+        Ok(())
+    }
+
+    /// Blocks until a run is available for execution. The run counts as in
+    /// flight until the returned guard is dropped, which also happens when
+    /// the runner thread panics.
+    pub fn pop(&self) -> (QueuedRun, InFlight<'_>) {
+        let mut inner = self.inner.lock().unwrap();
+        loop {
+            if let Some(queued) = inner.pending.pop_front() {
+                inner.in_flight += 1;
+                return (queued, InFlight { queue: self });
+            }
+            inner = self.available.wait(inner).unwrap();
+        }
+    }
+
+    /// Whether a popped run was cancelled by a newer trigger.
+    pub fn is_cancelled(&self, queued: &QueuedRun) -> bool {
+        let inner = self.inner.lock().unwrap();
+
+        self.config.queue_policy == QueuePolicy::CancelInFlight
+            && queued.generation < inner.generation
+    }
+
+    /// Registers a runner thread consuming this queue.
+    pub fn attach(&self) {
+        self.inner.lock().unwrap().consumers += 1;
+    }
+
+    /// Unregisters a runner thread. Once the last one is gone the queue
+    /// rejects new triggers and drops the waiting ones.
+    pub fn detach(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consumers -= 1;
+        if inner.consumers == 0 {
+            inner.pending.clear();
+        }
+    }
+
+    /// Returns `(waiting, executing)` trigger counts.
+    pub fn depth(&self) -> (usize, usize) {
+        let inner = self.inner.lock().unwrap();
+        (inner.pending.len(), inner.in_flight)
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.config.max_concurrency.max(1)
+    }
+}
+
+pub struct InFlight<'a> {
+    queue: &'a RunQueue,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.queue.inner.lock() {
+            inner.in_flight -= 1;
+        }
+    }
+}
+
+pub fn reply_all(replies: Vec<oneshot::Sender<RunOutcome>>, outcome: &RunOutcome) {
+    for reply in replies {
+        // The caller may have given up waiting, which is fine.
+        let _ = reply.send(outcome.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(queue_policy: QueuePolicy, max_queue: Option<usize>) -> RunQueue {
+        let queue = RunQueue::new(RunnerConfig {
+            queue_policy,
+            max_queue,
+            ..Default::default()
+        });
+        queue.attach();
+        queue
+    }
+
+    fn trigger(arg: &str) -> (Trigger, oneshot::Receiver<RunOutcome>) {
+        let (reply, reply_receiver) = oneshot::channel();
+        let trigger = Trigger {
+            payload: Some(RunPayload {
+                args: vec![arg.to_string()],
+                ..Default::default()
+            }),
+            reply: Some(reply),
+        };
+        (trigger, reply_receiver)
+    }
+
+    #[test]
+    fn fifo_runs_every_trigger_in_order() {
+        let queue = queue(QueuePolicy::Fifo, None);
+        for arg in ["a", "b", "a"] {
+            queue.push(trigger(arg).0).unwrap();
+        }
+        assert_eq!(queue.depth(), (3, 0));
+
+        for expected in ["a", "b", "a"] {
+            let (queued, _in_flight) = queue.pop();
+            assert_eq!(queued.payload.unwrap().args, vec![expected.to_string()]);
+        }
+    }
+
+    #[test]
+    fn coalesce_joins_waiting_trigger_with_same_payload() {
+        let queue = queue(QueuePolicy::Coalesce, None);
+        queue.push(trigger("a").0).unwrap();
+        queue.push(trigger("b").0).unwrap();
+        queue.push(trigger("a").0).unwrap();
+        assert_eq!(queue.depth(), (2, 0));
+
+        let (queued, _in_flight) = queue.pop();
+        assert_eq!(queued.replies.len(), 2);
+    }
+
+    #[test]
+    fn cancel_in_flight_cancels_older_runs() {
+        let queue = queue(QueuePolicy::CancelInFlight, None);
+        queue.push(trigger("a").0).unwrap();
+        let (running, in_flight) = queue.pop();
+        assert_eq!(queue.depth(), (0, 1));
+        assert!(!queue.is_cancelled(&running));
+
+        let (waiting, mut waiting_reply) = trigger("b");
+        queue.push(waiting).unwrap();
+        queue.push(trigger("c").0).unwrap();
+
+        // The waiting trigger is answered right away, the running one is
+        // left for its runner thread to stop.
+        assert_eq!(queue.depth(), (1, 1));
+        assert!(waiting_reply.try_recv().unwrap().error.is_some());
+        assert!(queue.is_cancelled(&running));
+
+        drop(in_flight);
+        let (latest, _in_flight) = queue.pop();
+        assert!(!queue.is_cancelled(&latest));
+        assert_eq!(queue.depth(), (0, 1));
+    }
+
+    #[test]
+    fn full_queue_rejects_triggers() {
+        let queue = queue(QueuePolicy::Fifo, Some(1));
+        queue.push(trigger("a").0).unwrap();
+
+        assert!(matches!(
+            queue.push(trigger("b").0),
+            Err(EnqueueError::Full)
+        ));
+    }
+
+    #[test]
+    fn queue_without_consumers_is_closed() {
+        let queue = queue(QueuePolicy::Fifo, None);
+        queue.push(trigger("a").0).unwrap();
+        queue.detach();
+
+        assert_eq!(queue.depth(), (0, 0));
+        assert!(matches!(
+            queue.push(trigger("b").0),
+            Err(EnqueueError::Closed)
+        ));
+    }
+}
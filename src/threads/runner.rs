@@ -0,0 +1,451 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use sqlite::Connection;
+
+use crate::{
+    config::{Config, ModuleConfig},
+    logs::{LogStream, ModuleLogs},
+    modules::{CheckModule, Execution},
+    persistency::{RunRecord, Save},
+    protocol::ResultStatus,
+    types::{ExitCodePolicy, LatencyStats, RunOutcome, RunnerState},
+};
+
+use super::{
+    log_execution, persist_key_value_pairs,
+    queue::{reply_all, QueuedRun, RunQueue},
+    supervisor, track_incident, unix_timestamp,
+};
+
+/// How often a running execution is checked for cancellation.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn spawn_runner_threads(
+    modules: Vec<Box<dyn CheckModule>>,
+    runner_states: Arc<Mutex<HashMap<String, RunnerState>>>,
+    runner_connection: Arc<Mutex<Connection>>,
+    module_logs: ModuleLogs,
+    exit_code_policy: ExitCodePolicy,
+    config: &Config,
+) {
+    for module in modules {
+        module_logs.register(module.name());
+        let queue = Arc::new(RunQueue::new(config.runner(module.name())));
+
+        runner_states.lock().unwrap().insert(
+            module.name().to_string(),
+            RunnerState {
+                module_name: module.name().to_string(),
+                status: ResultStatus::Unknown,
+                last_run: std::time::Instant::now(),
+                last_output: None,
+                load_error: None,
+                crash_reason: None,
+                restarts: 0,
+                latency: LatencyStats::default(),
+                queue: queue.clone(),
+            },
+        );
+
+        // Every thread of the pool runs its own instance of the module, and
+        // every restart after a panic gets a fresh one.
+        for _ in 0..queue.max_concurrency() {
+            let template = module.fresh_instance();
+            let module_name = template.name().to_string();
+            let panic_states = runner_states.clone();
+            let panic_connection = runner_connection.clone();
+            let runner_states = runner_states.clone();
+            let runner_connection = runner_connection.clone();
+            let module_logs = module_logs.clone();
+            let exit_code_policy = exit_code_policy.clone();
+            let module_config = config.module(module.name());
+            let queue = queue.clone();
+
+            queue.attach();
+            supervisor::spawn_supervised(
+                module_name.clone(),
+                move || {
+                    run_runner(
+                        template.as_ref(),
+                        runner_states.clone(),
+                        runner_connection.clone(),
+                        module_logs.clone(),
+                        exit_code_policy.clone(),
+                        &module_config,
+                        queue.clone(),
+                    )
+                },
+                move |message| {
+                    let reason = format!("Thread panicked: {}", message);
+                    track_incident(
+                        &panic_connection,
+                        &module_name,
+                        ResultStatus::Crash,
+                        Some(&reason),
+                    );
+                    update_runner_state(&panic_states, &module_name, |state| {
+                        state.status = ResultStatus::Crash;
+                        state.crash_reason = Some(reason);
+                        state.restarts += 1;
+                    });
+                },
+            );
+        }
+    }
+}
+
+fn run_runner(
+    template: &dyn CheckModule,
+    runner_states: Arc<Mutex<HashMap<String, RunnerState>>>,
+    runner_connection: Arc<Mutex<Connection>>,
+    module_logs: ModuleLogs,
+    exit_code_policy: ExitCodePolicy,
+    module_config: &ModuleConfig,
+    queue: Arc<RunQueue>,
+) {
+    let mut module = match load_instance(template, &runner_states, &runner_connection, &module_logs)
+    {
+        Some(val) => val,
+        None => {
+            queue.detach();
+            return;
+        }
+    };
+
+    loop {
+        let (queued, in_flight) = queue.pop();
+        let started_at = unix_timestamp();
+
+        let started = std::time::Instant::now();
+        let result = match execute_cancellable(module, &queued, &queue) {
+            Some((instance, result)) => {
+                module = instance;
+                result
+            }
+            None => {
+                drop(in_flight);
+                let outcome = RunOutcome::cancelled();
+                record_run(
+                    &runner_connection,
+                    template.name(),
+                    started_at,
+                    &queued,
+                    &outcome,
+                );
+                reply_all(queued.replies, &outcome);
+
+                // The cancelled instance is left behind, the next run gets a
+                // fresh one.
+                module =
+                    match load_instance(template, &runner_states, &runner_connection, &module_logs)
+                    {
+                        Some(val) => val,
+                        None => {
+                            queue.detach();
+                            return;
+                        }
+                    };
+                continue;
+            }
+        };
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let outcome = match result {
+            Ok(execution) => {
+                if queue.is_cancelled(&queued) {
+                    RunOutcome::cancelled()
+                } else {
+                    apply_execution(
+                        module.as_ref(),
+                        execution,
+                        duration_ms,
+                        &runner_states,
+                        &runner_connection,
+                        &module_logs,
+                        |exit_code, status| {
+                            module_config.degrade(
+                                exit_code_policy.evaluate(exit_code, status, ResultStatus::Ok),
+                                duration_ms,
+                            )
+                        },
+                    )
+                }
+            }
+            Err(err) => {
+                eprintln!("Error: Could not execute module {}: {}", module.name(), err);
+                module_logs.append(module.name(), LogStream::Host, &err);
+                update_runner_state(&runner_states, module.name(), |state| {
+                    state.status = ResultStatus::Crash;
+                    state.latency.record(duration_ms);
+                    state.last_run = std::time::Instant::now();
+                    state.crash_reason = Some(err.clone());
+                });
+                RunOutcome {
+                    status: ResultStatus::Crash,
+                    error: Some(err),
+                    duration_ms: Some(duration_ms),
+                    exit_code: None,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    output: None,
+                }
+            }
+        };
+        drop(in_flight);
+
+        let message = outcome.error.as_deref().or(outcome
+            .output
+            .as_ref()
+            .and_then(|output| output.message.as_deref()));
+        track_incident(&runner_connection, module.name(), outcome.status, message);
+        record_run(
+            &runner_connection,
+            module.name(),
+            started_at,
+            &queued,
+            &outcome,
+        );
+        reply_all(queued.replies, &outcome);
+    }
+}
+
+/// Loads a new instance of the runner's module, marking the runner crashed
+/// if that fails.
+fn load_instance(
+    template: &dyn CheckModule,
+    runner_states: &Arc<Mutex<HashMap<String, RunnerState>>>,
+    runner_connection: &Arc<Mutex<Connection>>,
+    module_logs: &ModuleLogs,
+) -> Option<Box<dyn CheckModule>> {
+    let mut module = template.fresh_instance();
+    if let Err(err) = module.load() {
+        eprintln!("Error: Could not load module {}: {}", module.name(), err);
+        module_logs.append(module.name(), LogStream::Host, &err);
+        track_incident(
+            runner_connection,
+            module.name(),
+            ResultStatus::Crash,
+            Some(&err),
+        );
+        update_runner_state(runner_states, module.name(), |state| {
+            state.status = ResultStatus::Crash;
+            state.load_error = Some(err.clone());
+            state.crash_reason = Some(err);
+        });
+        return None;
+    }
+    Some(module)
+}
+
+/// Executes a run on its own thread while watching for a newer trigger
+/// cancelling it. Returns the module with the result, or None once the run
+/// was cancelled: the execution is then interrupted if the backend supports
+/// it, or otherwise left to finish in the background with the module.
+fn execute_cancellable(
+    mut module: Box<dyn CheckModule>,
+    queued: &QueuedRun,
+    queue: &RunQueue,
+) -> Option<(Box<dyn CheckModule>, Result<Execution, String>)> {
+    let interrupt = module.interrupt();
+    let payload = queued.payload.clone();
+    let (done_sender, done_receiver) = mpsc::channel();
+    let execution = std::thread::spawn(move || {
+        let result = module.execute(payload.as_ref());
+        // The runner is gone if the run was cancelled meanwhile.
+        let _ = done_sender.send((module, result));
+    });
+
+    loop {
+        match done_receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+            Ok(done) => return Some(done),
+            Err(RecvTimeoutError::Timeout) => {
+                if queue.is_cancelled(queued) {
+                    if let Some(interrupt) = interrupt {
+                        interrupt();
+                    }
+                    return None;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                // The execution thread only hangs up without a result when
+                // it panicked, which is passed on to the runner thread.
+                let panic = execution
+                    .join()
+                    .expect_err("execution thread exited without a result");
+                std::panic::resume_unwind(panic);
+            }
+        }
+    }
+}
+
+fn apply_execution(
+    module: &dyn CheckModule,
+    execution: Execution,
+    duration_ms: f64,
+    runner_states: &Arc<Mutex<HashMap<String, RunnerState>>>,
+    runner_connection: &Arc<Mutex<Connection>>,
+    module_logs: &ModuleLogs,
+    status_of: impl FnOnce(Option<i32>, Option<ResultStatus>) -> ResultStatus,
+) -> RunOutcome {
+    log_execution(module_logs, module.name(), &execution);
+
+    let output = module.parse(&execution);
+    persist_key_value_pairs(&output.kv, runner_connection);
+
+    let status = status_of(execution.exit_code, output.status);
+    update_runner_state(runner_states, module.name(), |state| {
+        state.status = status;
+        state.latency.record(duration_ms);
+        state.last_run = std::time::Instant::now();
+        state.last_output = Some(output.clone());
+        state.crash_reason = None;
+    });
+
+    RunOutcome {
+        status,
+        error: None,
+        duration_ms: Some(duration_ms),
+        exit_code: execution.exit_code,
+        stdout: execution.stdout,
+        stderr: execution.stderr,
+        output: Some(output),
+    }
+}
+
+fn record_run(
+    runner_connection: &Arc<Mutex<Connection>>,
+    module_name: &str,
+    started_at: u64,
+    queued: &QueuedRun,
+    outcome: &RunOutcome,
+) {
+    let run_record = RunRecord {
+        module_name: module_name.to_string(),
+        started_at,
+        finished_at: unix_timestamp(),
+        success: outcome.status.is_up(),
+        payload: queued
+            .payload
+            .as_ref()
+            .and_then(|payload| serde_json::to_string(payload).ok()),
+        error: outcome.error.clone(),
+    };
+
+    if let Ok(connection) = runner_connection.lock() {
+        if let Err(err) = run_record.persist(&connection) {
+            eprintln!("Error: Could not record run of {}: {}", module_name, err);
+        }
+    }
+}
+
+fn update_runner_state(
+    runner_states: &Arc<Mutex<HashMap<String, RunnerState>>>,
+    module_name: &str,
+    update: impl FnOnce(&mut RunnerState),
+) {
+    if let Ok(mut states) = runner_states.lock() {
+        if let Some(state) = states.get_mut(module_name) {
+            update(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::{
+        config::{QueuePolicy, RunnerConfig},
+        modules::Interrupt,
+        types::{RunPayload, Trigger},
+    };
+
+    use super::*;
+
+    /// Keeps executing until it is interrupted.
+    struct BlockingModule {
+        interrupted: Arc<AtomicBool>,
+    }
+
+    impl CheckModule for BlockingModule {
+        fn name(&self) -> &str {
+            "blocking"
+        }
+
+        fn fresh_instance(&self) -> Box<dyn CheckModule> {
+            Box::new(BlockingModule {
+                interrupted: self.interrupted.clone(),
+            })
+        }
+
+        fn load(&mut self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn execute(&mut self, _payload: Option<&RunPayload>) -> Result<Execution, String> {
+            while !self.interrupted.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Ok(Execution {
+                exit_code: None,
+                stdout: "True".to_string(),
+                stderr: String::new(),
+                metrics: HashMap::new(),
+            })
+        }
+
+        fn interrupt(&self) -> Option<Interrupt> {
+            let interrupted = self.interrupted.clone();
+            Some(Box::new(move || interrupted.store(true, Ordering::SeqCst)))
+        }
+    }
+
+    fn running_queue() -> (RunQueue, QueuedRun) {
+        let queue = RunQueue::new(RunnerConfig {
+            queue_policy: QueuePolicy::CancelInFlight,
+            ..Default::default()
+        });
+        queue.attach();
+        queue.push(Trigger::default()).unwrap();
+        let (queued, in_flight) = queue.pop();
+        drop(in_flight);
+        (queue, queued)
+    }
+
+    #[test]
+    fn newer_trigger_interrupts_running_execution() {
+        let (queue, queued) = running_queue();
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let module = Box::new(BlockingModule {
+            interrupted: interrupted.clone(),
+        });
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(100));
+                queue.push(Trigger::default()).unwrap();
+            });
+            assert!(execute_cancellable(module, &queued, &queue).is_none());
+        });
+        assert!(interrupted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn finished_execution_hands_module_back() {
+        let (queue, queued) = running_queue();
+        let module = Box::new(BlockingModule {
+            interrupted: Arc::new(AtomicBool::new(true)),
+        });
+
+        let (module, result) = execute_cancellable(module, &queued, &queue).unwrap();
+        assert_eq!(module.name(), "blocking");
+        assert_eq!(result.unwrap().stdout, "True");
+    }
+}
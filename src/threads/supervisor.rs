@@ -0,0 +1,56 @@
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+/// Delay before the first restart, doubled after every further panic.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound of the restart delay. A thread that stayed up for longer than
+/// this is considered healthy again and restarts from the initial delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Spawns a thread running `body` and restarts it with exponential backoff
+/// whenever it panics. `on_panic` is called with the panic message before
+/// every restart. The thread ends once `body` returns normally.
+pub fn spawn_supervised(
+    module_name: String,
+    mut body: impl FnMut() + Send + 'static,
+    on_panic: impl Fn(&str) + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            let started = Instant::now();
+            let panic_payload = match panic::catch_unwind(AssertUnwindSafe(&mut body)) {
+                Ok(_) => return,
+                Err(val) => val,
+            };
+
+            let message = panic_message(panic_payload.as_ref());
+            eprintln!(
+                "Error: Thread of module {} panicked: {}, restarting in {:?}",
+                module_name, message, backoff
+            );
+            on_panic(&message);
+
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = INITIAL_BACKOFF;
+            }
+            std::thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use sqlite::Connection;
+
+use crate::{
+    config::{Config, ModuleConfig},
+    logs::{LogStream, ModuleLogs},
+    modules::CheckModule,
+    protocol::ResultStatus,
+    types::{ExitCodePolicy, LatencyStats, WorkerStates},
+};
+
+use super::{log_execution, persist_key_value_pairs, record_status, supervisor, track_incident};
+
+pub fn spawn_worker_threads(
+    modules: Vec<Box<dyn CheckModule>>,
+    worker_states: Arc<Mutex<HashMap<String, WorkerStates>>>,
+    worker_connection: Arc<Mutex<Connection>>,
+    module_logs: ModuleLogs,
+    exit_code_policy: ExitCodePolicy,
+    config: &Config,
+) {
+    for module in modules {
+        module_logs.register(module.name());
+
+        worker_states.lock().unwrap().insert(
+            module.name().to_string(),
+            WorkerStates {
+                status: ResultStatus::Unknown,
+                exit_code: None,
+                last_output: None,
+                load_error: None,
+                crash_reason: None,
+                restarts: 0,
+                latency: LatencyStats::default(),
+            },
+        );
+
+        let module_name = module.name().to_string();
+        let panic_states = worker_states.clone();
+        let panic_connection = worker_connection.clone();
+        let worker_states = worker_states.clone();
+        let worker_connection = worker_connection.clone();
+        let module_logs = module_logs.clone();
+        let exit_code_policy = exit_code_policy.clone();
+        let module_config = config.module(module.name());
+
+        // Every (re)start gets a fresh instance so a panic cannot leave the
+        // module in a half-initialised state.
+        supervisor::spawn_supervised(
+            module_name.clone(),
+            move || {
+                run_worker(
+                    module.fresh_instance(),
+                    worker_states.clone(),
+                    worker_connection.clone(),
+                    module_logs.clone(),
+                    exit_code_policy.clone(),
+                    &module_config,
+                )
+            },
+            move |message| {
+                record_status(&panic_connection, &module_name, ResultStatus::Crash);
+                let reason = format!("Thread panicked: {}", message);
+                track_incident(
+                    &panic_connection,
+                    &module_name,
+                    ResultStatus::Crash,
+                    Some(&reason),
+                );
+                update_worker_state(&panic_states, &module_name, |state| {
+                    state.status = ResultStatus::Crash;
+                    state.crash_reason = Some(reason);
+                    state.restarts += 1;
+                });
+            },
+        );
+    }
+}
+
+fn run_worker(
+    mut module: Box<dyn CheckModule>,
+    worker_states: Arc<Mutex<HashMap<String, WorkerStates>>>,
+    worker_connection: Arc<Mutex<Connection>>,
+    module_logs: ModuleLogs,
+    exit_code_policy: ExitCodePolicy,
+    module_config: &ModuleConfig,
+) {
+    if let Err(err) = module.load() {
+        eprintln!("Error: Could not load module {}: {}", module.name(), err);
+        module_logs.append(module.name(), LogStream::Host, &err);
+        record_status(&worker_connection, module.name(), ResultStatus::Crash);
+        track_incident(
+            &worker_connection,
+            module.name(),
+            ResultStatus::Crash,
+            Some(&err),
+        );
+        update_worker_state(&worker_states, module.name(), |state| {
+            state.status = ResultStatus::Crash;
+            state.load_error = Some(err.clone());
+            state.crash_reason = Some(err);
+        });
+        return;
+    }
+
+    loop {
+        let started = std::time::Instant::now();
+        let result = module.execute(None);
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        match result {
+            Ok(execution) => {
+                log_execution(&module_logs, module.name(), &execution);
+                let output = module.parse(&execution);
+                persist_key_value_pairs(&output.kv, &worker_connection);
+
+                let status = module_config.degrade(
+                    exit_code_policy.evaluate(
+                        execution.exit_code,
+                        output.status,
+                        ResultStatus::Unknown,
+                    ),
+                    duration_ms,
+                );
+                record_status(&worker_connection, module.name(), status);
+                track_incident(
+                    &worker_connection,
+                    module.name(),
+                    status,
+                    output.message.as_deref(),
+                );
+                update_worker_state(&worker_states, module.name(), |state| {
+                    state.status = status;
+                    state.latency.record(duration_ms);
+                    state.exit_code = execution.exit_code;
+                    state.last_output = Some(output);
+                    state.crash_reason = None;
+                });
+            }
+            Err(err) => {
+                eprintln!("Error: Could not execute module {}: {}", module.name(), err);
+                module_logs.append(module.name(), LogStream::Host, &err);
+                record_status(&worker_connection, module.name(), ResultStatus::Crash);
+                track_incident(
+                    &worker_connection,
+                    module.name(),
+                    ResultStatus::Crash,
+                    Some(&err),
+                );
+                update_worker_state(&worker_states, module.name(), |state| {
+                    state.status = ResultStatus::Crash;
+                    state.latency.record(duration_ms);
+                    state.exit_code = None;
+                    state.crash_reason = Some(err);
+                });
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_secs(60));
+    }
+}
+
+fn update_worker_state(
+    worker_states: &Arc<Mutex<HashMap<String, WorkerStates>>>,
+    module_name: &str,
+    update: impl FnOnce(&mut WorkerStates),
+) {
+    if let Ok(mut states) = worker_states.lock() {
+        if let Some(state) = states.get_mut(module_name) {
+            update(state);
+        }
+    }
+}
@@ -1,41 +1,274 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    protocol::{ModuleOutput, ResultStatus},
+    threads::RunQueue,
+};
+
 #[derive(Debug)]
-pub struct WasmWorker {
-    pub module_name: String,
-    pub bytes: Vec<u8>,
+pub struct WorkerStates {
+    pub status: ResultStatus,
+    pub exit_code: Option<i32>,
+    pub last_output: Option<ModuleOutput>,
+    /// Why the module could not be loaded, e.g. a missing symbol.
+    pub load_error: Option<String>,
+    /// Why the module is on crash when the host knows it, e.g. a load error
+    /// or the message of a panic in its thread.
+    pub crash_reason: Option<String>,
+    pub restarts: u32,
+    pub latency: LatencyStats,
 }
 
-pub struct WasmRunner {
-    pub module_name: String,
-    pub bytes: Vec<u8>,
+/// Number of recent execution durations percentiles are computed over.
+pub const LATENCY_SAMPLES: usize = 100;
+
+/// Durations of the executions of a module, as measured by the host.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LatencyStats {
+    #[serde(skip)]
+    samples: VecDeque<f64>,
+    pub last_ms: Option<f64>,
+    /// Executions measured since the start.
+    pub count: u64,
+    pub sum_ms: f64,
 }
 
-pub struct DLLRunner {
-    pub module_name: String,
-    pub path: String,
+impl LatencyStats {
+    pub fn record(&mut self, duration_ms: f64) {
+        if self.samples.len() == LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration_ms);
+        self.last_ms = Some(duration_ms);
+        self.count += 1;
+        self.sum_ms += duration_ms;
+    }
+
+    /// Nearest-rank percentile over the last [`LATENCY_SAMPLES`] executions,
+    /// `quantile` being between 0 and 1.
+    pub fn percentile(&self, quantile: f64) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(|left, right| left.total_cmp(right));
+        let rank = (quantile * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            last_ms: self.last_ms,
+            p50_ms: self.percentile(0.5),
+            p90_ms: self.percentile(0.9),
+            p99_ms: self.percentile(0.99),
+            count: self.count,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct WorkerStates {
-    pub on_crash: bool,
-    pub alive: bool,
+#[derive(Clone, Debug, Serialize)]
+pub struct LatencySummary {
+    pub last_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub count: u64,
+}
+
+/// Maps the WASI exit code of a worker execution to its health.
+///
+/// Codes listed in `healthy` mark the service ok, codes in `warning` mark it
+/// degraded, codes in `unhealthy` mark it critical and every other code is
+/// treated as a crash of the health check.
+#[derive(Clone, Debug)]
+pub struct ExitCodePolicy {
+    pub healthy: Vec<i32>,
+    pub warning: Vec<i32>,
+    pub unhealthy: Vec<i32>,
+}
+
+impl Default for ExitCodePolicy {
+    fn default() -> Self {
+        ExitCodePolicy {
+            healthy: vec![0],
+            warning: Vec::new(),
+            unhealthy: vec![1],
+        }
+    }
+}
+
+impl ExitCodePolicy {
+    /// Returns the status of an execution.
+    ///
+    /// A healthy exit code defers to the status the module printed, if any, so
+    /// modules still using the stdout contract keep working. Backends without
+    /// an exit code are judged by the printed status alone, and by `missing`
+    /// when the module printed none: a finished runner counts as ok, a worker
+    /// as unknown.
+    pub fn evaluate(
+        &self,
+        exit_code: Option<i32>,
+        status: Option<ResultStatus>,
+        missing: ResultStatus,
+    ) -> ResultStatus {
+        match exit_code {
+            Some(code) if self.healthy.contains(&code) => status.unwrap_or(ResultStatus::Ok),
+            Some(code) if self.warning.contains(&code) => ResultStatus::Warning,
+            Some(code) if self.unhealthy.contains(&code) => ResultStatus::Critical,
+            Some(_) => ResultStatus::Crash,
+            None => status.unwrap_or(missing),
+        }
+    }
 }
 
 pub struct RunnerState {
     pub module_name: String,
+    /// Status of the last run, unknown until the first one finished.
+    pub status: ResultStatus,
     pub last_run: std::time::Instant,
-    pub last_run_success: bool,
-    pub channel_trigger: std::sync::mpsc::Sender<()>,
+    pub last_output: Option<ModuleOutput>,
+    pub load_error: Option<String>,
+    pub crash_reason: Option<String>,
+    pub restarts: u32,
+    pub latency: LatencyStats,
+    pub queue: std::sync::Arc<RunQueue>,
 }
 
-pub struct NativeWorkerStates {
-    pub on_crash: bool,
-    pub alive: bool,
+/// Per-run parameters sent in the body of a thunder request.
+///
+/// Wasm runners get `args` as their arguments, `env` as environment
+/// variables and the whole payload as JSON on stdin. Native runners get `env`
+/// appended to their environment string and, if they export
+/// `health_check_runner_payload`, the payload JSON as a second argument to
+/// `start`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct RunPayload {
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
 }
 
-pub struct NativeStates {
-    pub module_name: String,
-    pub on_crash: bool,
-    pub last_run: std::time::Instant,
-    pub last_run_success: bool,
-    pub channel_trigger: std::sync::mpsc::Sender<()>,
+/// A request to execute a runner once.
+#[derive(Default)]
+pub struct Trigger {
+    pub payload: Option<RunPayload>,
+    /// Receives the outcome once the execution finished, for callers that
+    /// wait for the run instead of firing and forgetting.
+    pub reply: Option<tokio::sync::oneshot::Sender<RunOutcome>>,
+}
+
+/// The result of a single runner execution.
+#[derive(Clone, Debug, Serialize)]
+pub struct RunOutcome {
+    pub status: ResultStatus,
+    pub error: Option<String>,
+    /// Time the execution took, None when it was cancelled.
+    pub duration_ms: Option<f64>,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub output: Option<ModuleOutput>,
+}
+
+impl RunOutcome {
+    /// Outcome handed to callers whose run was superseded by a newer trigger.
+    pub fn cancelled() -> Self {
+        RunOutcome {
+            status: ResultStatus::Unknown,
+            error: Some("Run was cancelled by a newer trigger".to_string()),
+            duration_ms: None,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            output: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_decides_before_printed_status() {
+        let policy = ExitCodePolicy {
+            healthy: vec![0],
+            warning: vec![3],
+            unhealthy: vec![1],
+        };
+
+        assert_eq!(
+            policy.evaluate(Some(0), None, ResultStatus::Unknown),
+            ResultStatus::Ok
+        );
+        assert_eq!(
+            policy.evaluate(Some(0), Some(ResultStatus::Critical), ResultStatus::Unknown),
+            ResultStatus::Critical
+        );
+        assert_eq!(
+            policy.evaluate(Some(3), Some(ResultStatus::Ok), ResultStatus::Unknown),
+            ResultStatus::Warning
+        );
+        assert_eq!(
+            policy.evaluate(Some(1), Some(ResultStatus::Ok), ResultStatus::Unknown),
+            ResultStatus::Critical
+        );
+        assert_eq!(
+            policy.evaluate(Some(139), None, ResultStatus::Unknown),
+            ResultStatus::Crash
+        );
+    }
+
+    #[test]
+    fn missing_status_without_exit_code_uses_fallback() {
+        let policy = ExitCodePolicy::default();
+
+        // A native runner that only prints KV lines still ran successfully.
+        assert_eq!(
+            policy.evaluate(None, None, ResultStatus::Ok),
+            ResultStatus::Ok
+        );
+        assert_eq!(
+            policy.evaluate(None, None, ResultStatus::Unknown),
+            ResultStatus::Unknown
+        );
+        assert_eq!(
+            policy.evaluate(None, Some(ResultStatus::Crash), ResultStatus::Ok),
+            ResultStatus::Crash
+        );
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let mut latency = LatencyStats::default();
+        assert_eq!(latency.percentile(0.5), None);
+
+        for duration_ms in [40.0, 10.0, 30.0, 20.0] {
+            latency.record(duration_ms);
+        }
+
+        assert_eq!(latency.percentile(0.0), Some(10.0));
+        assert_eq!(latency.percentile(0.5), Some(20.0));
+        assert_eq!(latency.percentile(0.9), Some(40.0));
+        assert_eq!(latency.percentile(1.0), Some(40.0));
+        assert_eq!(latency.last_ms, Some(20.0));
+    }
+
+    #[test]
+    fn percentile_only_covers_recent_samples() {
+        let mut latency = LatencyStats::default();
+        for duration_ms in 1..=LATENCY_SAMPLES as u64 + 50 {
+            latency.record(duration_ms as f64);
+        }
+
+        // The first 50 samples dropped out of the window, the totals did not.
+        assert_eq!(latency.percentile(0.0), Some(51.0));
+        assert_eq!(latency.percentile(0.99), Some(149.0));
+        assert_eq!(latency.count, LATENCY_SAMPLES as u64 + 50);
+        assert_eq!(latency.summary().p50_ms, Some(100.0));
+    }
 }
@@ -0,0 +1,232 @@
+//! Availability of worker modules, computed from the status of every
+//! execution recorded in the `status_history` table.
+//!
+//! Ok and warning count as up; critical and crash count as down. Unknown
+//! executions, e.g. a killed plugin, are left out of the calculation.
+
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    persistency::{StatusRecord, STATUS_HISTORY_RETENTION_SECS},
+    protocol::ResultStatus,
+};
+
+/// A period uptime is computed over, written as a number and a unit of `m`,
+/// `h` or `d`, e.g. `24h`. Windows cannot be longer than the status history
+/// is kept.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Window {
+    pub label: String,
+    pub secs: u64,
+}
+
+impl TryFrom<String> for Window {
+    type Error = String;
+
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        let unit_secs = match label.chars().last() {
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            _ => return Err(format!("Window {} does not end in m, h or d", label)),
+        };
+        let amount = &label[..label.len() - 1];
+        let amount = match amount.parse::<u64>() {
+            Ok(val) if val > 0 => val,
+            _ => return Err(format!("Window {} has no valid length", label)),
+        };
+
+        let secs = match amount.checked_mul(unit_secs) {
+            Some(val) if val <= STATUS_HISTORY_RETENTION_SECS => val,
+            _ => {
+                return Err(format!(
+                    "Window {} is longer than the {} days of status history kept",
+                    label,
+                    STATUS_HISTORY_RETENTION_SECS / (24 * 60 * 60)
+                ))
+            }
+        };
+
+        Ok(Window { secs, label })
+    }
+}
+
+pub fn default_windows() -> Vec<Window> {
+    ["1h", "24h", "7d", "30d"]
+        .into_iter()
+        .filter_map(|label| Window::try_from(label.to_string()).ok())
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WindowUptime {
+    pub window: String,
+    /// Executions the uptime is based on.
+    pub samples: u64,
+    pub uptime_percent: Option<f64>,
+    /// How fast the error budget is spent; 1 spends exactly the budget over
+    /// the window.
+    pub burn_rate: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct UptimeReport {
+    pub module: String,
+    pub slo_target_percent: Option<f64>,
+    /// Budget left over the longest window, negative once it is overspent.
+    pub error_budget_remaining_percent: Option<f64>,
+    pub windows: Vec<WindowUptime>,
+}
+
+pub fn report(
+    conn: &sqlite::Connection,
+    module_name: &str,
+    windows: &[Window],
+    slo_target_percent: Option<f64>,
+    now: u64,
+) -> Result<UptimeReport, Box<dyn Error>> {
+    // Without a budget there is nothing to burn.
+    let allowed_error_rate = slo_target_percent
+        .filter(|target| *target < 100.0)
+        .map(|target| 1.0 - target / 100.0);
+
+    let mut window_uptimes = Vec::new();
+    for window in windows {
+        let counts = StatusRecord::count_since(conn, module_name, now.saturating_sub(window.secs))?;
+
+        let (mut up, mut samples) = (0, 0);
+        for (status, count) in counts {
+            if status == ResultStatus::Unknown.as_str() {
+                continue;
+            }
+            if status == ResultStatus::Ok.as_str() || status == ResultStatus::Warning.as_str() {
+                up += count;
+            }
+            samples += count;
+        }
+
+        let uptime = (samples > 0).then(|| up as f64 / samples as f64);
+        window_uptimes.push(WindowUptime {
+            window: window.label.clone(),
+            samples,
+            uptime_percent: uptime.map(|uptime| uptime * 100.0),
+            burn_rate: uptime
+                .zip(allowed_error_rate)
+                .map(|(uptime, allowed)| (1.0 - uptime) / allowed),
+        });
+    }
+
+    let longest = windows
+        .iter()
+        .zip(window_uptimes.iter())
+        .max_by_key(|(window, _)| window.secs)
+        .map(|(_, window_uptime)| window_uptime);
+
+    Ok(UptimeReport {
+        module: module_name.to_string(),
+        slo_target_percent,
+        error_budget_remaining_percent: longest
+            .and_then(|window_uptime| window_uptime.burn_rate)
+            .map(|burn_rate| (1.0 - burn_rate) * 100.0),
+        windows: window_uptimes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::persistency::Save;
+
+    use super::*;
+
+    const HOUR: u64 = 60 * 60;
+
+    fn record(
+        conn: &sqlite::Connection,
+        module_name: &str,
+        recorded_at: u64,
+        status: ResultStatus,
+    ) {
+        StatusRecord {
+            module_name: module_name.to_string(),
+            recorded_at,
+            status,
+        }
+        .persist(conn)
+        .unwrap();
+    }
+
+    #[test]
+    fn parses_window_labels() {
+        let window = Window::try_from("90m".to_string()).unwrap();
+        assert_eq!(window.secs, 90 * 60);
+        assert_eq!(Window::try_from("24h".to_string()).unwrap().secs, 24 * HOUR);
+        assert_eq!(
+            Window::try_from("31d".to_string()).unwrap().secs,
+            31 * 24 * HOUR
+        );
+
+        for label in ["", "h", "0h", "-1h", "1w", "1.5h", "1é"] {
+            assert!(Window::try_from(label.to_string()).is_err(), "{}", label);
+        }
+    }
+
+    #[test]
+    fn rejects_windows_beyond_retention() {
+        assert!(Window::try_from("32d".to_string()).is_err());
+        assert!(Window::try_from(format!("{}d", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn reports_uptime_and_burn_rate_per_window() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let now = 100 * 24 * HOUR;
+
+        // Last hour: 3 up (one degraded), 1 down, 1 unknown left out.
+        record(&conn, "web", now - 10, ResultStatus::Ok);
+        record(&conn, "web", now - 20, ResultStatus::Warning);
+        record(&conn, "web", now - 30, ResultStatus::Ok);
+        record(&conn, "web", now - 40, ResultStatus::Critical);
+        record(&conn, "web", now - 50, ResultStatus::Unknown);
+        // Earlier in the day: 4 up.
+        for offset in 1..=4 {
+            record(&conn, "web", now - offset * 2 * HOUR, ResultStatus::Ok);
+        }
+        record(&conn, "other", now - 10, ResultStatus::Crash);
+
+        let windows = vec![
+            Window::try_from("1h".to_string()).unwrap(),
+            Window::try_from("24h".to_string()).unwrap(),
+        ];
+        let report = report(&conn, "web", &windows, Some(90.0), now).unwrap();
+
+        assert_eq!(report.windows[0].samples, 4);
+        assert_eq!(report.windows[0].uptime_percent, Some(75.0));
+        let burn_rate = report.windows[0].burn_rate.unwrap();
+        assert!((burn_rate - 2.5).abs() < 1e-9);
+
+        assert_eq!(report.windows[1].samples, 8);
+        assert_eq!(report.windows[1].uptime_percent, Some(87.5));
+        // The budget over the longest window is 10%, 12.5% were spent.
+        let remaining = report.error_budget_remaining_percent.unwrap();
+        assert!((remaining + 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reports_nothing_without_samples_or_target() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let windows = default_windows();
+
+        let report = report(&conn, "web", &windows, None, 1_000_000).unwrap();
+
+        assert_eq!(report.windows.len(), 4);
+        assert!(report.windows.iter().all(|window| window.samples == 0));
+        assert!(report
+            .windows
+            .iter()
+            .all(|window| window.uptime_percent.is_none()));
+        assert_eq!(report.error_budget_remaining_percent, None);
+    }
+}